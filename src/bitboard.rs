@@ -0,0 +1,206 @@
+/*
+    Bitboards for the 5x5 board, the representation moves are generated from.
+
+    Each colour and kind of piece has a u32 mask with bit row * 5 + col set for every square one is on, row 0 being
+    rank 5 as on the mailbox board. Board keeps the masks up to date as squares change, like its hash. A piece's
+    moves come from tables of the squares each kind of piece could move to from each square, worked out at compile
+    time, masked by which squares are empty or hold an opposing piece.
+*/
+
+use crate::{grid_to_one_d, Colour, Kind, PieceMove, SquareVal};
+
+const BOARD_MASK: u32 = (1 << 25) - 1;
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+
+const KNIGHT_MOVES: [u32; 25] = generate_knight_moves();
+
+// Indexed by colour then square, where a pawn can move straight forward to and where it can capture
+const PAWN_PUSHES: [[u32; 25]; 2] = generate_pawn_moves(&[0]);
+const PAWN_ATTACKS: [[u32; 25]; 2] = generate_pawn_moves(&[-1, 1]);
+
+// Indexed by colour, the rank each side's pawns are heading for
+const LAST_RANKS: [u32; 2] = [0b11111, 0b11111 << 20];
+
+#[derive(Copy, Clone, Default)]
+pub struct Bitboards {
+    // Indexed by colour then kind
+    masks: [[u32; 2]; 2]
+}
+
+const fn generate_knight_moves() -> [u32; 25] {
+    let mut moves = [0; 25];
+    let mut square = 0;
+    while square < 25 {
+        let mut offset = 0;
+        while offset < KNIGHT_OFFSETS.len() {
+            moves[square] |= get_target_mask(square, KNIGHT_OFFSETS[offset].0, KNIGHT_OFFSETS[offset].1);
+            offset += 1;
+        }
+        square += 1;
+    }
+    moves
+}
+
+// The squares one row forward and each of col_offsets columns across
+const fn generate_pawn_moves(col_offsets: &[i32]) -> [[u32; 25]; 2] {
+    let mut moves = [[0; 25]; 2];
+    let mut colour = 0;
+    while colour < 2 {
+        // White pawns head for rank 5, which is row 0
+        let forward = if colour == 0 { -1 } else { 1 };
+        let mut square = 0;
+        while square < 25 {
+            let mut offset = 0;
+            while offset < col_offsets.len() {
+                moves[colour][square] |= get_target_mask(square, forward, col_offsets[offset]);
+                offset += 1;
+            }
+            square += 1;
+        }
+        colour += 1;
+    }
+    moves
+}
+
+// The bit for the square the offsets away from square, or no bits if that's off the board
+const fn get_target_mask(square: usize, row_offset: i32, col_offset: i32) -> u32 {
+    let row = (square / 5) as i32 + row_offset;
+    let col = (square % 5) as i32 + col_offset;
+    if row < 0 || row >= 5 || col < 0 || col >= 5 {
+        return 0;
+    }
+    1 << (row * 5 + col)
+}
+
+impl Bitboards {
+    pub fn from_squares(squares: &[SquareVal; 63]) -> Bitboards {
+        let mut bitboards = Bitboards::default();
+        for (index, square_val) in squares.iter().enumerate() {
+            bitboards.update_square(index, &SquareVal::Empty, square_val);
+        }
+        bitboards
+    }
+    pub fn update_square(&mut self, index: usize, old_square_val: &SquareVal, new_square_val: &SquareVal) {
+        if let SquareVal::Piece(piece) = old_square_val {
+            self.masks[get_colour_index(&piece.colour)][get_kind_index(&piece.kind)] &= !get_bit(index);
+        }
+        if let SquareVal::Piece(piece) = new_square_val {
+            self.masks[get_colour_index(&piece.colour)][get_kind_index(&piece.kind)] |= get_bit(index);
+        }
+    }
+    pub fn get_pieces(&self, colour: &Colour, kind: &Kind) -> u32 {
+        self.masks[get_colour_index(colour)][get_kind_index(kind)]
+    }
+    fn get_occupied(&self, colour: &Colour) -> u32 {
+        self.get_pieces(colour, &Kind::Pawn) | self.get_pieces(colour, &Kind::Knight)
+    }
+    pub fn get_empty(&self) -> u32 {
+        !(self.get_occupied(&Colour::White) | self.get_occupied(&Colour::Black)) & BOARD_MASK
+    }
+    // Every move colour's pieces can make, in order of the square moved from
+    pub fn get_piece_moves(&self, colour: &Colour) -> Vec<PieceMove> {
+        let colour_index = get_colour_index(colour);
+        let pawns = self.get_pieces(colour, &Kind::Pawn);
+        let empty = self.get_empty();
+        let opponents = self.get_occupied(&-*colour);
+        get_bit_indices(self.get_occupied(colour))
+            .flat_map(|square| {
+                let targets = if pawns & (1 << square) != 0 {
+                    PAWN_PUSHES[colour_index][square] & empty | PAWN_ATTACKS[colour_index][square] & opponents
+                } else {
+                    KNIGHT_MOVES[square] & (empty | opponents)
+                };
+                get_bit_indices(targets).map(move |target| PieceMove {
+                    from_square: get_mailbox_index(square),
+                    to_square: get_mailbox_index(target)
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn get_last_rank(colour: &Colour) -> u32 {
+    LAST_RANKS[get_colour_index(colour)]
+}
+
+// The mailbox index of every square in the mask, in order
+pub fn get_mailbox_indices(mask: u32) -> impl Iterator<Item = u8> {
+    get_bit_indices(mask).map(get_mailbox_index)
+}
+
+pub fn get_bit(index: usize) -> u32 {
+    let offset = index - grid_to_one_d(0, 0);
+    1 << (offset / 7 * 5 + offset % 7)
+}
+
+fn get_bit_indices(mask: u32) -> impl Iterator<Item = usize> {
+    (0..25).filter(move |square| mask & (1 << square) != 0)
+}
+
+fn get_mailbox_index(square: usize) -> u8 {
+    grid_to_one_d(square / 5, square % 5) as u8
+}
+
+fn get_colour_index(colour: &Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1
+    }
+}
+
+fn get_kind_index(kind: &Kind) -> usize {
+    match kind {
+        Kind::Pawn => 0,
+        Kind::Knight => 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+    use crate::{dataset, Board, MovePair};
+
+    #[test]
+    fn tables_cover_the_squares_each_piece_can_reach() {
+        // a1 is bit 20, c2 bit 17 and b3 bit 11
+        assert_eq!(KNIGHT_MOVES[20], 1 << 17 | 1 << 11);
+        // c3 is bit 12, a white pawn there goes to c4 and captures on b4 and d4
+        assert_eq!(PAWN_PUSHES[0][12], 1 << 7);
+        assert_eq!(PAWN_ATTACKS[0][12], 1 << 6 | 1 << 8);
+        // A black pawn on the a file only captures towards the b file
+        assert_eq!(PAWN_ATTACKS[1][10], 1 << 16);
+        // Nothing is forward of a pawn on its last rank
+        assert_eq!(PAWN_PUSHES[1][22] | PAWN_ATTACKS[1][22], 0);
+    }
+
+    #[test]
+    fn masks_follow_the_squares() {
+        let board = dataset::parse_board("n...p..............PN....").unwrap();
+        assert_eq!(board.bitboards.get_pieces(&Colour::Black, &Kind::Knight), 1 << 0);
+        assert_eq!(board.bitboards.get_pieces(&Colour::Black, &Kind::Pawn), 1 << 4);
+        assert_eq!(board.bitboards.get_pieces(&Colour::White, &Kind::Pawn), 1 << 19);
+        assert_eq!(board.bitboards.get_pieces(&Colour::White, &Kind::Knight), 1 << 20);
+        assert_eq!(board.bitboards.get_empty().count_ones(), 21);
+    }
+
+    // Board checks the bitboard moves against the mailbox ones in debug builds, so this plays through lots of
+    // positions to give it the chance to find a difference
+    #[test]
+    fn moves_match_the_mailbox_generator_through_random_games() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let mut board = Board::new();
+            for _ in 0..100 {
+                let white_moves = board.get_all_legal_moves(Colour::White);
+                let black_moves = board.get_all_legal_moves(Colour::Black);
+                let (Some(white), Some(black)) = (white_moves.choose(&mut rng), black_moves.choose(&mut rng)) else {
+                    break;
+                };
+                board.execute_moves(MovePair { white: *white, black: *black });
+                assert_eq!(board.bitboards.masks, Bitboards::from_squares(&board.board).masks);
+            }
+        }
+    }
+}
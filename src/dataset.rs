@@ -0,0 +1,94 @@
+/*
+    Positions labelled with how the game they came from ended, for fitting the evaluation to.
+
+    A dataset is a text file with one position per line: the board, then the result for White (1 for a win, 0.5 for
+    a draw and 0 for a loss), separated by a space. Anything after those is ignored, so files with more on each line
+    can be used as they are. Blank lines and lines starting with # are skipped.
+
+    Boards are written as 25 characters, rank 5 to rank 1 and file a to e within each rank, the same order the board
+    is printed in. P and N are White's pawns and knights, p and n are Black's and . is an empty square.
+*/
+
+use std::{fs, process};
+use crate::{grid_to_one_d, Board, Colour, Kind, Piece, SquareVal};
+
+pub struct LabelledPosition {
+    pub board: Board,
+    pub result: f64
+}
+
+pub fn read_dataset(path: &str) -> Result<Vec<LabelledPosition>, String> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(line_index, line)| {
+            parse_labelled_position(line).ok_or_else(|| format!("Invalid position on line {} of {}", line_index + 1, path))
+        })
+        .collect()
+}
+
+// For the commands that fit to a dataset, which have nothing to fall back on if it can't be read or written
+pub fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+fn parse_labelled_position(line: &str) -> Option<LabelledPosition> {
+    let mut fields = line.split_whitespace();
+    let board = parse_board(fields.next()?)?;
+    let result = match fields.next()? {
+        "1" => 1.0,
+        "0.5" => 0.5,
+        "0" => 0.0,
+        _ => return None
+    };
+    Some(LabelledPosition { board, result })
+}
+
+pub fn format_board(board: &Board) -> String {
+    (0..5)
+        .flat_map(|row| (0..5).map(move |col| grid_to_one_d(row, col)))
+        .map(|index| match board.board[index] {
+            SquareVal::Piece(piece) => get_piece_char(&piece),
+            _ => '.'
+        })
+        .collect()
+}
+
+pub fn parse_board(text: &str) -> Option<Board> {
+    if text.chars().count() != 25 {
+        return None;
+    }
+    let mut board = Board::new();
+    for (square, ch) in text.chars().enumerate() {
+        let square_val = match ch {
+            '.' => SquareVal::Empty,
+            _ => SquareVal::Piece(get_piece(ch)?)
+        };
+        board.set_square(grid_to_one_d(square / 5, square % 5) as u8, square_val);
+    }
+    Some(board)
+}
+
+fn get_piece_char(piece: &Piece) -> char {
+    let ch = match piece.kind {
+        Kind::Pawn => 'p',
+        Kind::Knight => 'n'
+    };
+    match piece.colour {
+        Colour::White => ch.to_ascii_uppercase(),
+        Colour::Black => ch
+    }
+}
+
+fn get_piece(ch: char) -> Option<Piece> {
+    let kind = match ch.to_ascii_lowercase() {
+        'p' => Kind::Pawn,
+        'n' => Kind::Knight,
+        _ => return None
+    };
+    let colour = if ch.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+    Some(Piece { colour, kind })
+}
@@ -0,0 +1,478 @@
+/*
+    Board evaluation for the search.
+
+    An evaluator scores a board from one side's point of view as a sum of named terms, positive being good for that
+    side. The search only needs the total, while the breakdown is there to see why a board got the score it did.
+    The search is generic over the evaluator, so a different evaluation can be plugged in by implementing Evaluator.
+
+    The game can use either the piece-square evaluation here or a trained network (see network.rs).
+
+    A side loses when its last footman (pawn) is gone, so on top of the tables the piece-square evaluation values
+    each side's footmen by how many it has left, more so the fewer there are, and looks at which pawns the opponent
+    could capture with its next move. It also looks at what each side's pieces can do from where they stand: how many
+    moves it has, how many squares it attacks and how many of the opponent's pieces its knights and pawns attack.
+    Those terms are scaled by weights, and any of them can be turned off by giving it a weight of 0.
+
+    The piece-square tables can be loaded from a TOML file holding a pawn_values_white, pawn_values_black and
+    knight_values array. Each has a value for all 63 squares of the mailbox board, which must be 0 off the board and
+    no more than MAX_TABLE_VALUE either way on it. The file can also have a [weights] table setting any of mobility,
+    attacked_squares, knight_threats and pawn_threats, with the rest keeping their defaults.
+*/
+
+use std::fs;
+use serde::Deserialize;
+use crate::{get_pawn_direction, is_right_colour_footman, network::NetworkEvaluator, Board, Colour, Kind, Move, Piece, PieceMove, SquareVal};
+
+pub trait Evaluator: Sync {
+    fn get_terms(&self, board: &Board, colour: Colour) -> Vec<Term>;
+    // Called at every leaf of the search, so worth overriding to skip building the breakdown
+    fn evaluate(&self, board: &Board, colour: Colour) -> f64 {
+        self.get_terms(board, colour).iter().map(|term| term.value).sum()
+    }
+}
+
+pub struct Term {
+    pub name: &'static str,
+    pub value: f64
+}
+
+// Material plus a bonus for where each piece stands, looked up from a table per piece
+#[derive(Clone, Copy)]
+pub struct PieceSquareEvaluator {
+    pub pawn_values_white: [f64; 63],
+    pub pawn_values_black: [f64; 63],
+    pub knight_values: [f64; 63],
+    pub weights: TermWeights
+}
+
+// How much each of the terms worked out from the pieces' moves counts, per move, square or piece attacked
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TermWeights {
+    pub mobility: f64,
+    pub attacked_squares: f64,
+    pub knight_threats: f64,
+    pub pawn_threats: f64
+}
+
+// The evaluator picked on the command line
+pub enum EvaluatorChoice {
+    PieceSquare(Box<PieceSquareEvaluator>),
+    Network(NetworkEvaluator)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TablesFile {
+    pawn_values_white: Vec<f64>,
+    pawn_values_black: Vec<f64>,
+    knight_values: Vec<f64>,
+    #[serde(default)]
+    weights: TermWeights
+}
+
+// What a side's footmen are worth on top of their table values, by how many it has left. Losing the last one loses
+// the game, so it's worth more than any knight
+const FOOTMAN_COUNT_VALUES: [f64; 6] = [0.0, 4.0, 6.0, 7.0, 7.5, 7.5];
+const SAFE_PAWN_BONUS: f64 = 0.1;
+// How much of a pawn's worth, footman value included, is counted as lost when the opponent can capture it
+pub const THREATENED_PAWN_SHARE: f64 = 0.75;
+// Table values from a file are kept to this size either way, so that even with every piece on the board no evaluation
+// comes near search::WIN_VALUE, which the search relies on to tell won and lost positions from the rest
+const MAX_TABLE_VALUE: f64 = 20.0;
+
+const PAWN_VALUES_WHITE: [f64; 63] = [
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 1.2, 1.4, 1.2, 1.0, 0.0,
+    0.0, 1.2, 1.4, 1.6, 1.4, 1.2, 0.0,
+    0.0, 1.4, 1.6, 1.8, 1.6, 1.4, 0.0,
+    0.0, 1.6, 1.8, 2.0, 1.8, 1.6, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0
+];
+
+const PAWN_VALUES_BLACK: [f64; 63] = [
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 1.6, 1.8, 2.0, 1.8, 1.6, 0.0,
+    0.0, 1.4, 1.6, 1.8, 1.6, 1.4, 0.0,
+    0.0, 1.2, 1.4, 1.6, 1.4, 1.2, 0.0,
+    0.0, 1.0, 1.2, 1.4, 1.2, 1.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0
+];
+
+const KNIGHT_VALUES: [f64; 63] = [
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 3.0, 3.2, 3.4, 3.2, 3.0, 0.0,
+    0.0, 3.2, 3.4, 3.6, 3.4, 3.2, 0.0,
+    0.0, 3.4, 3.6, 3.8, 3.6, 3.4, 0.0,
+    0.0, 3.2, 3.4, 3.6, 3.4, 3.2, 0.0,
+    0.0, 3.0, 3.2, 3.4, 3.2, 3.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+];
+
+impl Default for PieceSquareEvaluator {
+    fn default() -> PieceSquareEvaluator {
+        PieceSquareEvaluator {
+            pawn_values_white: PAWN_VALUES_WHITE,
+            pawn_values_black: PAWN_VALUES_BLACK,
+            knight_values: KNIGHT_VALUES,
+            weights: TermWeights::default()
+        }
+    }
+}
+
+impl Default for TermWeights {
+    fn default() -> TermWeights {
+        TermWeights {
+            mobility: 0.05,
+            attacked_squares: 0.05,
+            knight_threats: 0.3,
+            pawn_threats: 0.2
+        }
+    }
+}
+
+impl PieceSquareEvaluator {
+    pub fn load(path: &str) -> Result<PieceSquareEvaluator, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+        let file: TablesFile = toml::from_str(&contents).map_err(|err| format!("Couldn't parse {}: {}", path, err))?;
+        Ok(PieceSquareEvaluator {
+            pawn_values_white: get_table("pawn_values_white", &file.pawn_values_white)?,
+            pawn_values_black: get_table("pawn_values_black", &file.pawn_values_black)?,
+            knight_values: get_table("knight_values", &file.knight_values)?,
+            weights: get_weights(file.weights)?
+        })
+    }
+    // The tables and weights in the format load reads, the tables laid out a rank per line like the built-in ones
+    pub fn get_toml(&self) -> String {
+        let tables: Vec<String> = [
+            ("pawn_values_white", &self.pawn_values_white),
+            ("pawn_values_black", &self.pawn_values_black),
+            ("knight_values", &self.knight_values)
+        ]
+            .iter()
+            .map(|(name, table)| {
+                let rows: Vec<String> = table
+                    .chunks(7)
+                    .map(|row| {
+                        let values: Vec<String> = row.iter().map(|value| format!("{:?}", value)).collect();
+                        format!("    {},\n", values.join(", "))
+                    })
+                    .collect();
+                format!("{} = [\n{}]\n", name, rows.concat())
+            })
+            .collect();
+        let weights = format!(
+            "[weights]\nmobility = {:?}\nattacked_squares = {:?}\nknight_threats = {:?}\npawn_threats = {:?}\n",
+            self.weights.mobility, self.weights.attacked_squares, self.weights.knight_threats, self.weights.pawn_threats
+        );
+        format!("{}\n{}", tables.join("\n"), weights)
+    }
+    fn get_square_val(&self, element: (usize, &SquareVal), colour: &Colour) -> f64 {
+        match element.1 {
+            SquareVal::Piece(piece) => self.get_piece_val(piece, element.0, colour),
+            _ => 0.0
+        }
+    }
+    fn get_piece_val(&self, piece: &Piece, index: usize, colour: &Colour) -> f64 {
+        let absolute_piece_value = self.get_absolute_piece_value(piece, index);
+        if piece.colour == *colour {
+            absolute_piece_value
+        } else {
+            -absolute_piece_value
+        }
+    }
+    fn get_absolute_piece_value(&self, piece: &Piece, index: usize) -> f64 {
+        match piece.kind {
+            Kind::Knight => self.knight_values[index],
+            Kind::Pawn => self.get_pawn_val(piece, index)
+        }
+    }
+    fn get_pawn_val(&self, piece: &Piece, index: usize) -> f64 {
+        match piece.colour {
+            Colour::White => self.pawn_values_white[index],
+            Colour::Black => self.pawn_values_black[index]
+        }
+    }
+    fn get_kind_value(&self, board: &Board, kind: Kind, colour: &Colour) -> f64 {
+        board.board
+            .iter()
+            .enumerate()
+            .filter(|(_, square_val)| matches!(square_val, SquareVal::Piece(piece) if piece.kind == kind))
+            .fold(0.0, |acc, ele| acc + self.get_square_val(ele, colour))
+    }
+    // opponent_moves are all the moves colour's opponent has
+    fn get_pawn_safety(&self, board: &Board, colour: Colour, opponent_moves: &[PieceMove]) -> f64 {
+        let attacked_squares: Vec<u8> = opponent_moves.iter().map(|mov| mov.to_square).collect();
+        let footmen = get_footman_count(board, colour);
+        let last_footman_value = get_footman_count_value(footmen) - get_footman_count_value(footmen.saturating_sub(1));
+        board.board
+            .iter()
+            .enumerate()
+            .filter_map(|(index, square_val)| match square_val {
+                SquareVal::Piece(piece) if is_right_colour_footman(square_val, &colour) => Some((index, piece)),
+                _ => None
+            })
+            .map(|(index, piece)| {
+                if attacked_squares.contains(&(index as u8)) {
+                    -THREATENED_PAWN_SHARE * (self.get_pawn_val(piece, index) + last_footman_value)
+                } else {
+                    SAFE_PAWN_BONUS
+                }
+            })
+            .sum()
+    }
+}
+
+impl Evaluator for PieceSquareEvaluator {
+    fn get_terms(&self, board: &Board, colour: Colour) -> Vec<Term> {
+        let our_moves = get_piece_moves(board.get_all_legal_moves(colour));
+        let their_moves = get_piece_moves(board.get_all_legal_moves(-colour));
+        let attacked_squares = get_attacked_squares(board, colour, &our_moves).len() as f64
+            - get_attacked_squares(board, -colour, &their_moves).len() as f64;
+        let knight_threats = count_threats(board, &our_moves, Kind::Knight, &[Kind::Pawn, Kind::Knight])
+            - count_threats(board, &their_moves, Kind::Knight, &[Kind::Pawn, Kind::Knight]);
+        // A pawn that can take one of the opponent's pawns can always be taken by it as well, so those threats cancel
+        // out and it's the knights pawns attack that make a difference
+        let pawn_threats = count_threats(board, &our_moves, Kind::Pawn, &[Kind::Pawn, Kind::Knight])
+            - count_threats(board, &their_moves, Kind::Pawn, &[Kind::Pawn, Kind::Knight]);
+        vec![
+            Term { name: "pawns", value: self.get_kind_value(board, Kind::Pawn, &colour) },
+            Term { name: "knights", value: self.get_kind_value(board, Kind::Knight, &colour) },
+            Term { name: "footmen", value: get_footmen_value(board, colour) },
+            Term {
+                name: "pawn safety",
+                value: self.get_pawn_safety(board, colour, &their_moves) - self.get_pawn_safety(board, -colour, &our_moves)
+            },
+            Term { name: "mobility", value: self.weights.mobility * (our_moves.len() as f64 - their_moves.len() as f64) },
+            Term { name: "attacked squares", value: self.weights.attacked_squares * attacked_squares },
+            Term { name: "knight threats", value: self.weights.knight_threats * knight_threats },
+            Term { name: "pawn threats", value: self.weights.pawn_threats * pawn_threats }
+        ]
+    }
+}
+
+impl Evaluator for EvaluatorChoice {
+    fn get_terms(&self, board: &Board, colour: Colour) -> Vec<Term> {
+        match self {
+            EvaluatorChoice::PieceSquare(evaluator) => evaluator.get_terms(board, colour),
+            EvaluatorChoice::Network(evaluator) => evaluator.get_terms(board, colour)
+        }
+    }
+    fn evaluate(&self, board: &Board, colour: Colour) -> f64 {
+        match self {
+            EvaluatorChoice::PieceSquare(evaluator) => evaluator.evaluate(board, colour),
+            EvaluatorChoice::Network(evaluator) => evaluator.evaluate(board, colour)
+        }
+    }
+}
+
+fn get_footmen_value(board: &Board, colour: Colour) -> f64 {
+    get_footman_count_value(get_footman_count(board, colour)) - get_footman_count_value(get_footman_count(board, -colour))
+}
+
+fn get_footman_count(board: &Board, colour: Colour) -> usize {
+    board.board.iter().filter(|square_val| is_right_colour_footman(square_val, &colour)).count()
+}
+
+fn get_footman_count_value(footmen: usize) -> f64 {
+    FOOTMAN_COUNT_VALUES[footmen.min(FOOTMAN_COUNT_VALUES.len() - 1)]
+}
+
+// Every square colour's pieces could capture on next move, whether or not there's anything there to capture. moves
+// are all the moves colour has, which cover where the knights can go but not the pawns' diagonals onto empty squares
+fn get_attacked_squares(board: &Board, colour: Colour, moves: &[PieceMove]) -> Vec<u8> {
+    let forward = get_pawn_direction(&colour) as isize;
+    let mut squares: Vec<u8> = moves
+        .iter()
+        .filter(|mov| matches!(board.board[mov.from_square as usize], SquareVal::Piece(piece) if piece.kind == Kind::Knight))
+        .map(|mov| mov.to_square)
+        .chain(board.board
+            .iter()
+            .enumerate()
+            .filter(|(_, square_val)| is_right_colour_footman(square_val, &colour))
+            .flat_map(|(index, _)| [index as isize + forward - 1, index as isize + forward + 1])
+            .filter(|index| !matches!(board.board[*index as usize], SquareVal::Invalid))
+            .map(|index| index as u8))
+        .collect();
+    squares.sort_unstable();
+    squares.dedup();
+    squares
+}
+
+// Passing doesn't count as a move for any of the terms
+fn get_piece_moves(moves: Vec<Move>) -> Vec<PieceMove> {
+    moves
+        .into_iter()
+        .filter_map(|mov| match mov {
+            Move::Piece(piece_move) => Some(piece_move),
+            Move::Pass => None
+        })
+        .collect()
+}
+
+// How many of the moves are a piece of the attacking kind capturing one of the target kinds
+fn count_threats(board: &Board, moves: &[PieceMove], attacker: Kind, targets: &[Kind]) -> f64 {
+    moves
+        .iter()
+        .filter(|mov| {
+            matches!(board.board[mov.from_square as usize], SquareVal::Piece(piece) if piece.kind == attacker)
+                && matches!(board.board[mov.to_square as usize], SquareVal::Piece(piece) if targets.contains(&piece.kind))
+        })
+        .count() as f64
+}
+
+fn get_weights(weights: TermWeights) -> Result<TermWeights, String> {
+    let values = [weights.mobility, weights.attacked_squares, weights.knight_threats, weights.pawn_threats];
+    if values.iter().any(|value| !value.is_finite()) {
+        return Err("weights must all be numbers".to_string());
+    }
+    Ok(weights)
+}
+
+// Checks a table from a file covers the whole mailbox board, with nothing on the padding squares around it
+fn get_table(name: &str, values: &[f64]) -> Result<[f64; 63], String> {
+    let table: [f64; 63] = values
+        .try_into()
+        .map_err(|_| format!("{} has {} values, it needs one for each of the 63 squares", name, values.len()))?;
+    let padding = Board::new().board;
+    for (index, value) in table.iter().enumerate() {
+        if !value.is_finite() {
+            return Err(format!("{} has {} for square {}", name, value, index));
+        }
+        if value.abs() > MAX_TABLE_VALUE {
+            return Err(format!("{} has {} for square {}, which is more than {} either way", name, value, index, MAX_TABLE_VALUE));
+        }
+        if matches!(padding[index], SquareVal::Invalid) && *value != 0.0 {
+            return Err(format!("{} has {} for square {}, which is off the board and must be 0", name, value, index));
+        }
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+    use crate::{dataset, search::{self, SearchLimits}};
+
+    // Black's last pawn can take the knight on b2, but then the pawn on a1 takes it back, or it promotes, and either
+    // way Black has no footmen left
+    #[test]
+    fn keeps_last_footman_rather_than_trading_it_for_a_knight() {
+        let board = dataset::parse_board("....n.......p...N...P...P").unwrap();
+        for max_depth in 1..=2 {
+            let limits = SearchLimits { max_depth, time_budget: None, node_budget: None, table_size: 0, threads: 1 };
+            let search_result = search::search(&board, Colour::Black, &limits, &PieceSquareEvaluator::default()).unwrap();
+            let capture_prob: f64 = search_result.moves
+                .iter()
+                .zip(search_result.strategy.iter())
+                .filter(|(mov, _)| matches!(mov, Move::Piece(mov) if mov.from_square == 31 && mov.to_square == 37))
+                .map(|(_, prob)| prob)
+                .sum();
+            assert!(capture_prob < 0.01, "played the capture with probability {} at depth {}", capture_prob, max_depth);
+        }
+    }
+
+    #[test]
+    fn table_with_a_value_off_the_board_is_rejected() {
+        assert!(get_table("knight_values", &KNIGHT_VALUES).is_ok());
+        let mut values = KNIGHT_VALUES;
+        // The square left of a5
+        values[14] = 0.5;
+        assert!(get_table("knight_values", &values).is_err());
+    }
+
+    #[test]
+    fn table_with_a_value_too_big_for_the_search_is_rejected() {
+        let mut values = KNIGHT_VALUES;
+        values[17] = search::WIN_VALUE - 1.0;
+        assert!(get_table("knight_values", &values).is_err());
+        values[17] = -MAX_TABLE_VALUE;
+        assert!(get_table("knight_values", &values).is_ok());
+    }
+
+    #[test]
+    fn table_of_the_wrong_length_is_rejected() {
+        assert!(get_table("knight_values", &KNIGHT_VALUES[..62]).is_err());
+        assert!(get_table("knight_values", &[KNIGHT_VALUES.as_slice(), &[0.0]].concat()).is_err());
+    }
+
+    #[test]
+    fn saved_tables_load_back_unchanged() {
+        let mut tables = PieceSquareEvaluator {
+            weights: TermWeights { mobility: 0.123456789, attacked_squares: 0.0, knight_threats: 0.5, pawn_threats: 1.0 / 3.0 },
+            ..PieceSquareEvaluator::default()
+        };
+        // Values like a tuner leaves, which don't round to a few decimal places
+        tables.pawn_values_white[17] = 1.0 / 3.0;
+        tables.pawn_values_black[38] = -0.000012345678;
+        tables.knight_values[31] = 3.7654321098765;
+        let path = env::temp_dir().join(format!("babylon-tables-test-{}.toml", process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, tables.get_toml()).unwrap();
+        let loaded = PieceSquareEvaluator::load(path);
+        fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.pawn_values_white, tables.pawn_values_white);
+        assert_eq!(loaded.pawn_values_black, tables.pawn_values_black);
+        assert_eq!(loaded.knight_values, tables.knight_values);
+        assert_eq!(loaded.get_toml(), tables.get_toml());
+    }
+
+    fn get_term(evaluator: &PieceSquareEvaluator, board: &Board, colour: Colour, name: &str) -> f64 {
+        evaluator.get_terms(board, colour).iter().find(|term| term.name == name).unwrap().value
+    }
+
+    // White's knight on c3 attacks both of Black's pawns, which are stuck behind White's, and White's pawn on e3
+    // attacks Black's knight on d4
+    fn get_fork() -> Board {
+        dataset::parse_board(&"....n p..np P.N.P ..... .....".replace(' ', "")).unwrap()
+    }
+
+    #[test]
+    fn knight_fork_counts_a_threat_for_each_piece_attacked() {
+        let evaluator = PieceSquareEvaluator::default();
+        assert_eq!(get_term(&evaluator, &get_fork(), Colour::White, "knight threats"), 2.0 * evaluator.weights.knight_threats);
+        assert_eq!(get_term(&evaluator, &get_fork(), Colour::Black, "knight threats"), -2.0 * evaluator.weights.knight_threats);
+        assert_eq!(get_term(&evaluator, &get_fork(), Colour::White, "pawn threats"), evaluator.weights.pawn_threats);
+    }
+
+    #[test]
+    fn search_plays_the_knight_fork() {
+        // b1-c3 forks the pawns, b1-d2 is the only other move White has
+        let board = dataset::parse_board(&"..n.. p...p P...P ..... .N...".replace(' ', "")).unwrap();
+        for max_depth in 1..=2 {
+            let limits = SearchLimits { max_depth, time_budget: None, node_budget: None, table_size: 0, threads: 1 };
+            let search_result = search::search(&board, Colour::White, &limits, &PieceSquareEvaluator::default()).unwrap();
+            let index = search_result.moves.iter().position(|mov| mov.to_string() == "b1-c3").unwrap();
+            assert!(search_result.strategy[index] > 0.99, "{:?}", search_result.strategy);
+        }
+    }
+
+    #[test]
+    fn weight_of_zero_turns_a_term_off() {
+        let evaluator = PieceSquareEvaluator::default();
+        let weights = evaluator.weights;
+        for (name, weights) in [
+            ("mobility", TermWeights { mobility: 0.0, ..weights }),
+            ("attacked squares", TermWeights { attacked_squares: 0.0, ..weights }),
+            ("knight threats", TermWeights { knight_threats: 0.0, ..weights }),
+            ("pawn threats", TermWeights { pawn_threats: 0.0, ..weights })
+        ] {
+            let without = PieceSquareEvaluator { weights, ..evaluator };
+            let value = get_term(&evaluator, &get_fork(), Colour::White, name);
+            assert_ne!(value, 0.0, "{} is already 0", name);
+            assert_eq!(get_term(&without, &get_fork(), Colour::White, name), 0.0);
+            let change = evaluator.evaluate(&get_fork(), Colour::White) - without.evaluate(&get_fork(), Colour::White);
+            assert!((change - value).abs() < 1e-9, "{} changed the evaluation by {} rather than {}", name, change, value);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+/*
+    The positions a game has been through, for the ways a game can end that depend on more than the board in front
+    of us.
+
+    A game is drawn when the same position comes up for the third time, when the set number of move pairs have been
+    played without a capture or a pawn moving, or when it reaches its move pair limit if it has one. Positions are
+    compared by their Zobrist hash. A side can also lose without the board having a say, by resigning or running
+    out of time.
+*/
+
+use crate::{is_right_colour_footman, Board, Colour, DrawReason, GameState, SquareVal, WinReason};
+
+// How many times a position has to come up for the game to be drawn
+const REPETITIONS_FOR_DRAW: usize = 3;
+
+pub struct GameHistory {
+    hashes: Vec<u64>,
+    board: Board,
+    // Move pairs played since the last capture or pawn move
+    quiet_move_pairs: u32,
+    no_progress_limit: u32,
+    max_move_pairs: Option<u32>,
+    // The side that resigned or ran out of time
+    forfeit: Option<(Colour, WinReason)>
+}
+
+impl GameHistory {
+    pub fn new(board: &Board, no_progress_limit: u32, max_move_pairs: Option<u32>) -> GameHistory {
+        GameHistory {
+            hashes: vec![board.hash],
+            board: *board,
+            quiet_move_pairs: 0,
+            no_progress_limit,
+            max_move_pairs,
+            forfeit: None
+        }
+    }
+    // Records the board after a move pair
+    pub fn push(&mut self, board: &Board) {
+        if is_progress(&self.board, board) {
+            self.quiet_move_pairs = 0;
+        } else {
+            self.quiet_move_pairs += 1;
+        }
+        self.hashes.push(board.hash);
+        self.board = *board;
+    }
+    pub fn resign(&mut self, colour: Colour) {
+        self.forfeit = Some((colour, WinReason::Resignation));
+    }
+    pub fn time_out(&mut self, colour: Colour) {
+        self.forfeit = Some((colour, WinReason::Timeout));
+    }
+    // The state of the game at the last board pushed
+    pub fn get_game_state(&self) -> GameState {
+        if let Some((loser, reason)) = self.forfeit {
+            return GameState::Win(-loser, reason);
+        }
+        let move_pairs = self.hashes.len() as u32 - 1;
+        match self.board.get_game_state() {
+            GameState::Ongoing if self.get_repetitions() >= REPETITIONS_FOR_DRAW => GameState::Draw(DrawReason::Repetition),
+            GameState::Ongoing if self.quiet_move_pairs >= self.no_progress_limit => GameState::Draw(DrawReason::NoProgress),
+            GameState::Ongoing if self.max_move_pairs.is_some_and(|max| move_pairs >= max) => GameState::Draw(DrawReason::MoveLimit),
+            game_state => game_state
+        }
+    }
+    fn get_repetitions(&self) -> usize {
+        self.hashes.iter().filter(|hash| **hash == self.board.hash).count()
+    }
+}
+
+// Whether a piece was captured or a pawn moved between the two boards
+fn is_progress(before: &Board, after: &Board) -> bool {
+    get_piece_count(before) != get_piece_count(after)
+        || before.board.iter().zip(after.board.iter()).any(|(before_square, after_square)| {
+            [Colour::White, Colour::Black].iter().any(|colour| {
+                is_right_colour_footman(before_square, colour) != is_right_colour_footman(after_square, colour)
+            })
+        })
+}
+
+fn get_piece_count(board: &Board) -> usize {
+    board.board.iter().filter(|square_val| matches!(square_val, SquareVal::Piece(_))).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dataset, Move, MovePair, PieceMove};
+
+    fn play(history: &mut GameHistory, board: &mut Board, white: (u8, u8), black: (u8, u8)) {
+        board.execute_moves(MovePair {
+            white: Move::Piece(PieceMove { from_square: white.0, to_square: white.1 }),
+            black: Move::Piece(PieceMove { from_square: black.0, to_square: black.1 })
+        });
+        history.push(board);
+    }
+
+    // Knights on a1 and a5 going to b3 and c4 and back, with the pawns never moving
+    fn shuffle_knights(history: &mut GameHistory, board: &mut Board) {
+        play(history, board, (43, 30), (15, 24));
+        play(history, board, (30, 43), (24, 15));
+    }
+
+    #[test]
+    fn third_time_a_position_comes_up_is_a_draw() {
+        let mut board = dataset::parse_board("n...p..............PN....").unwrap();
+        let mut history = GameHistory::new(&board, 50, None);
+        shuffle_knights(&mut history, &mut board);
+        assert!(matches!(history.get_game_state(), GameState::Ongoing));
+        shuffle_knights(&mut history, &mut board);
+        assert!(matches!(history.get_game_state(), GameState::Draw(DrawReason::Repetition)));
+    }
+
+    #[test]
+    fn too_long_without_a_capture_or_pawn_move_is_a_draw() {
+        let mut board = dataset::parse_board("n...p..............PN....").unwrap();
+        let mut history = GameHistory::new(&board, 3, None);
+        play(&mut history, &mut board, (43, 30), (15, 24));
+        play(&mut history, &mut board, (30, 43), (24, 15));
+        assert!(matches!(history.get_game_state(), GameState::Ongoing));
+        play(&mut history, &mut board, (43, 30), (15, 24));
+        assert!(matches!(history.get_game_state(), GameState::Draw(DrawReason::NoProgress)));
+    }
+
+    #[test]
+    fn reaching_the_move_pair_limit_is_a_draw() {
+        let mut board = dataset::parse_board("n...p..............PN....").unwrap();
+        let mut history = GameHistory::new(&board, 50, Some(2));
+        play(&mut history, &mut board, (43, 30), (15, 24));
+        assert!(matches!(history.get_game_state(), GameState::Ongoing));
+        play(&mut history, &mut board, (40, 33), (19, 26));
+        assert!(matches!(history.get_game_state(), GameState::Draw(DrawReason::MoveLimit)));
+    }
+
+    #[test]
+    fn side_that_resigns_loses() {
+        let mut history = GameHistory::new(&Board::new(), 50, None);
+        history.resign(Colour::White);
+        assert!(matches!(history.get_game_state(), GameState::Win(Colour::Black, WinReason::Resignation)));
+    }
+
+    #[test]
+    fn side_that_runs_out_of_time_loses() {
+        let mut history = GameHistory::new(&Board::new(), 50, None);
+        history.time_out(Colour::Black);
+        assert!(matches!(history.get_game_state(), GameState::Win(Colour::White, WinReason::Timeout)));
+    }
+
+    #[test]
+    fn pawn_move_resets_the_no_progress_count() {
+        let mut board = dataset::parse_board("n...p..............PN....").unwrap();
+        let mut history = GameHistory::new(&board, 3, None);
+        play(&mut history, &mut board, (43, 30), (15, 24));
+        play(&mut history, &mut board, (30, 43), (24, 15));
+        play(&mut history, &mut board, (43, 30), (19, 26));
+        assert!(matches!(history.get_game_state(), GameState::Ongoing));
+    }
+}
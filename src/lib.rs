@@ -0,0 +1,982 @@
+/*
+    The game and the engines that play it, used by the command line program in main.rs.
+
+    Board holds a position and plays move pairs on it. Anything implementing Evaluator can score positions for the
+    search, so an evaluator of your own plugs into search::search the same way the built in ones do.
+*/
+
+mod bitboard;
+mod dataset;
+pub mod evaluation;
+pub mod history;
+mod matrix_game;
+mod mcts;
+pub mod network;
+pub mod options;
+mod parallel;
+mod regret;
+pub mod search;
+pub mod selfplay;
+mod simplex;
+mod transposition;
+pub mod tuner;
+mod zobrist;
+
+pub use evaluation::{Evaluator, Term};
+pub use search::{search, SearchLimits, SearchResult};
+
+use bitboard::Bitboards;
+use rand::Rng;
+use std::{fmt, ops::Neg};
+
+#[derive(Copy, Clone)]
+pub struct Board {
+    board: [SquareVal; 63],
+    // Zobrist hash of the pieces on the board, kept up to date by set_square
+    hash: u64,
+    // Where each colour and kind of piece is, also kept up to date by set_square
+    bitboards: Bitboards,
+    // None unless playing with penalty points for illegal moves
+    pub penalty_points: Option<PenaltyPoints>,
+    pub pass_rule: PassRule
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct PenaltyPoints {
+    white: u8,
+    black: u8
+}
+
+// When a side may pass. Neither side having a piece it can move is always a draw.
+#[derive(Copy, Clone, PartialEq)]
+pub enum PassRule {
+    // Never, so a side with no legal moves draws the game
+    Never,
+    // Only when a side has no other legal moves, and the other side's move is played on its own
+    WhenBlocked,
+    // Whenever a side wants to
+    Always
+}
+
+#[derive(Copy, Clone)]
+pub enum SquareVal {
+    Invalid,
+    Empty, 
+    Piece(Piece),
+}
+
+#[derive(Copy, Clone)]
+pub struct Piece {
+    pub colour: Colour,
+    pub kind: Kind
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Colour {
+    White, 
+    Black
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum Kind {
+    Pawn,
+    Knight
+}
+
+#[derive(Copy, Clone)]
+pub enum Move {
+    // Standing still, when the pass rule allows it
+    Pass,
+    Piece(PieceMove)
+}
+
+#[derive(Copy, Clone)]
+pub struct PieceMove {
+    pub from_square: u8,
+    pub to_square: u8
+}
+
+#[derive(PartialEq)]
+enum MoveType {
+    Attack,
+    MoveOnly
+}
+
+#[derive(Clone, Copy)]
+pub struct MovePair {
+    pub white: Move,
+    pub black: Move
+}
+
+pub enum GameState {
+    Ongoing,
+    Draw(DrawReason),
+    Win(Colour, WinReason)
+}
+
+#[derive(Clone, Copy)]
+pub enum WinReason {
+    // The other side lost all its footmen
+    NoFootmen,
+    // The other side reached the penalty point limit
+    PenaltyPoints,
+    Resignation,
+    Timeout
+}
+
+#[derive(Clone, Copy)]
+pub enum DrawReason {
+    // Both sides lost their last footmen with the same move pair
+    MutualAnnihilation,
+    // A side has no legal moves, or neither side does when passing is allowed
+    Stalemate,
+    // Both sides reached the penalty point limit with the same move pair
+    PenaltyPoints,
+    Repetition,
+    // Too many move pairs without a capture or a pawn moving
+    NoProgress,
+    // The game went on for as many move pairs as it was allowed
+    MoveLimit
+}
+
+#[derive(Clone, Copy)]
+pub enum Engine {
+    Matrix(search::SearchLimits),
+    Mcts(mcts::MctsSettings),
+    Regret(regret::RegretSettings)
+}
+
+
+// A pawn reaching the last rank only becomes a knight while its side has fewer knights than this, otherwise it has to
+// be redeployed
+const MAX_KNIGHTS: usize = 2;
+
+// A side with this many penalty points loses
+const MAX_PENALTY_POINTS: u8 = 2;
+
+macro_rules! WhitePawn {
+    () => {
+        SquareVal::Piece(Piece {
+            colour: Colour::White,
+            kind: Kind::Pawn
+        })
+    };
+}
+
+macro_rules! WhiteKnight {
+    () => {
+        SquareVal::Piece(Piece {
+            colour: Colour::White,
+            kind: Kind::Knight
+        })
+    };
+}
+
+macro_rules! BlackPawn {
+    () => {
+        SquareVal::Piece(Piece {
+            colour: Colour::Black,
+            kind: Kind::Pawn
+        })
+    };
+}
+
+macro_rules! BlackKnight {
+    () => {
+        SquareVal::Piece(Piece {
+            colour: Colour::Black,
+            kind: Kind::Knight
+        })
+    };
+}
+
+impl Board {
+    pub fn new() -> Board {
+        let board = [SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid,
+                    SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid,
+                    SquareVal::Invalid, BlackKnight!(), BlackPawn!(), BlackPawn!(), BlackPawn!(), BlackKnight!(), SquareVal::Invalid,
+                    SquareVal::Invalid, BlackPawn!(), SquareVal::Empty, SquareVal::Empty, SquareVal::Empty, BlackPawn!(), SquareVal::Invalid,
+                    SquareVal::Invalid, SquareVal::Empty, SquareVal::Empty, SquareVal::Empty, SquareVal::Empty, SquareVal::Empty, SquareVal::Invalid,
+                    SquareVal::Invalid, WhitePawn!(), SquareVal::Empty, SquareVal::Empty, SquareVal::Empty, WhitePawn!(), SquareVal::Invalid,
+                    SquareVal::Invalid, WhiteKnight!(), WhitePawn!(), WhitePawn!(), WhitePawn!(), WhiteKnight!(), SquareVal::Invalid,
+                    SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid,
+                    SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid, SquareVal::Invalid];
+        Board {
+            hash: zobrist::hash_squares(&board),
+            bitboards: Bitboards::from_squares(&board),
+            board,
+            penalty_points: None,
+            pass_rule: PassRule::Never
+        }
+    }
+    fn set_square(&mut self, index: u8, square_val: SquareVal) {
+        let index = index as usize;
+        self.hash ^= zobrist::get_square_key(&self.board[index], index) ^ zobrist::get_square_key(&square_val, index);
+        self.bitboards.update_square(index, &self.board[index], &square_val);
+        self.board[index] = square_val;
+    }
+    // The square on the given row and column, with rows counted down from rank 5 as the board is displayed
+    pub fn get_square_at(&self, row: usize, col: usize) -> SquareVal {
+        self.board[grid_to_one_d(row, col)]
+    }
+    pub fn get_strategy<E: Evaluator, R: Rng>(&self, colour: Colour, engine: &Engine, evaluator: &E, rng: &mut R) -> SearchResult {
+        let search_result = match engine {
+            Engine::Matrix(limits) => search::search(self, colour, limits, evaluator),
+            Engine::Mcts(settings) => mcts::search(self, colour, settings, rng),
+            Engine::Regret(settings) => regret::search(self, colour, settings, rng)
+        };
+        search_result.unwrap_or_else(|| {
+            // Nothing to search, e.g. our opponent has no moves, so any move is as good as another
+            let moves = self.get_all_legal_moves(colour);
+            let strategy = vec![1.0 / moves.len() as f64; moves.len()];
+            SearchResult { moves, strategy }
+        })
+    }
+    pub fn get_all_legal_moves(&self, colour: Colour) -> Vec<Move> {
+        let mut moves: Vec<Move> = self.get_all_piece_moves(colour).into_iter().map(Move::Piece).collect();
+        if self.can_pass(moves.is_empty()) {
+            moves.push(Move::Pass);
+        }
+        moves
+    }
+    fn can_pass(&self, has_no_piece_moves: bool) -> bool {
+        // The side not redeploying a pawn waits, whatever the rule
+        if self.is_redeploying() {
+            return has_no_piece_moves;
+        }
+        match self.pass_rule {
+            PassRule::Never => false,
+            PassRule::WhenBlocked => has_no_piece_moves,
+            PassRule::Always => true
+        }
+    }
+    fn get_all_piece_moves(&self, colour: Colour) -> Vec<PieceMove> {
+        if self.is_redeploying() {
+            return self.get_redeploy_moves(&colour);
+        }
+        let piece_moves = self.bitboards.get_piece_moves(&colour);
+        debug_assert!(
+            have_same_moves(&piece_moves, &self.get_all_mailbox_moves(colour)),
+            "The bitboard and mailbox move generators disagree on {}'s moves for\n{}", colour, self
+        );
+        piece_moves
+    }
+    // The moves found by walking the mailbox board, which the bitboard moves are checked against
+    fn get_all_mailbox_moves(&self, colour: Colour) -> Vec<PieceMove> {
+        self.board
+            .iter()
+            .enumerate()
+            .flat_map(|(index, square_val)| {
+                // Index should never be greater than 62 and if it is, something has gone unrecoverably wrong
+                if index > 62 { panic!("Attempted to evaluate a board that was too large") };
+                let u8_index = index as u8;
+                match square_val {
+                    SquareVal::Piece(piece) => {
+                        if piece.colour == colour {
+                            self.get_piece_moves(piece, u8_index)
+                        } else {
+                            vec![]
+                        }
+                    },
+                    _ => vec![]
+                }
+            })
+            .collect()
+    } 
+    fn get_piece_moves(&self, piece: &Piece, index: u8) -> Vec<PieceMove> {
+        match piece.kind {
+            Kind::Pawn => self.get_pawn_moves(piece, index),
+            Kind::Knight => self.get_knight_moves(piece, index)
+        }
+    }
+    fn get_pawn_moves(&self, piece: &Piece, index: u8) -> Vec<PieceMove> {
+        let forward = get_pawn_direction(&piece.colour);
+        [(forward, MoveType::MoveOnly), (forward - 1, MoveType::Attack), (forward + 1, MoveType::Attack)]
+            .into_iter()
+            .map(|(offset, mov_type)| {
+                let mov = PieceMove { from_square: index, to_square: (index as i8 + offset) as u8 };
+                (mov, mov_type)
+            })
+            .filter(|(mov, mov_type)| self.is_legal_pawn_move(mov, mov_type, &piece.colour))
+            .map(|(mov, _)| mov)
+            .collect()
+    }
+    // A waiting pawn can go to any empty square except back onto its last rank, where it would only wait again
+    fn get_redeploy_moves(&self, colour: &Colour) -> Vec<PieceMove> {
+        let to_squares = self.bitboards.get_empty() & !bitboard::get_last_rank(colour);
+        bitboard::get_mailbox_indices(self.get_waiting_pawns(colour))
+            .flat_map(|from_square| {
+                bitboard::get_mailbox_indices(to_squares).map(move |to_square| PieceMove { from_square, to_square })
+            })
+            .collect()
+    }
+    fn get_knight_moves(&self, piece: &Piece, index: u8) -> Vec<PieceMove> {
+        ([13, 15, -13, -15, 5, 9, -5, -9] as [i8; 8])
+            .iter()
+            .map(|mov| PieceMove {
+                from_square: index,
+                to_square: (index as i8 + mov) as u8
+            })
+            .filter(|mov| self.is_legal_knight_move(mov, &piece.colour))
+            .collect()
+    }
+    fn is_legal_pawn_move(&self, mov: &PieceMove, mov_type: &MoveType, piece_colour: &Colour) -> bool {
+        match &self.board[mov.from_square as usize] {
+            SquareVal::Piece(Piece {
+                colour,
+                kind
+            }) => { if !(colour == piece_colour && kind == &Kind::Pawn) { return false }},
+            _ => { return false }
+        };
+        match self.board[mov.to_square as usize] {
+            SquareVal::Invalid => false,
+            SquareVal::Empty => *mov_type == MoveType::MoveOnly,
+            SquareVal::Piece(Piece {
+                colour,
+                kind: _
+            }) => colour != *piece_colour && *mov_type == MoveType::Attack,
+        }
+    }
+    fn is_legal_knight_move(&self, mov: &PieceMove, piece_colour: &Colour) -> bool {
+        match &self.board[mov.from_square as usize] {
+            SquareVal::Piece(Piece {
+                colour,
+                kind
+            }) => { if !(colour == piece_colour && kind == &Kind::Knight) { return false } },
+            _ => { return false }
+        };
+        match self.board[mov.to_square as usize] {
+            SquareVal::Invalid => false,
+            SquareVal::Empty => true,
+            SquareVal::Piece(Piece {
+                colour,
+                kind: _
+            }) => colour != *piece_colour,
+        }
+    }
+    // Both moves are made at once, each as it was chosen on the board before either was made. So a piece moving onto
+    // one that moves away at the same time doesn't capture it, and a pawn still makes a diagonal move whose target
+    // moved away. Only a piece that stays where it is gets captured. Pieces moving to the same square collide: two
+    // of the same kind are both removed, otherwise the knight takes the pawn.
+    // A side that passes stands still, and the other side's move is made on its own.
+    // Once both moves are made, a pawn that reached its last rank becomes a knight if its side has fewer than
+    // MAX_KNIGHTS. Otherwise it waits there, and the next move pair is only for redeploying it: its side moves it to an
+    // empty square while the other side waits. Pawns redeployed to the same square collide like any other pieces.
+    pub fn execute_moves(&mut self, mov_pair: MovePair) {
+        match (mov_pair.white, mov_pair.black) {
+            (Move::Piece(white), Move::Piece(black)) => self.execute_piece_moves(white, black),
+            (Move::Piece(white), Move::Pass) => self.execute_single_move(white),
+            (Move::Pass, Move::Piece(black)) => self.execute_single_move(black),
+            (Move::Pass, Move::Pass) => ()
+        }
+        self.promote_pawns();
+    }
+    fn execute_piece_moves(&mut self, white: PieceMove, black: PieceMove) {
+        if white.to_square == black.to_square {
+            self.execute_moves_to_same_square(white, black);
+        } else {
+            self.execute_moves_to_different_squares(white, black);
+        }
+    }
+    // Plays the moves each side submitted. With penalty points on, a side whose move is illegal gets a point and
+    // misses its turn, while the other side's move is still played. Returns the sides that got a point.
+    pub fn play_submitted_moves(&mut self, mov_pair: MovePair) -> Vec<Colour> {
+        let Some(mut penalty_points) = self.penalty_points else {
+            self.execute_moves(mov_pair);
+            return vec![];
+        };
+        let white_is_legal = self.is_legal_move(&mov_pair.white, &Colour::White);
+        let black_is_legal = self.is_legal_move(&mov_pair.black, &Colour::Black);
+        self.execute_moves(MovePair {
+            white: if white_is_legal { mov_pair.white } else { Move::Pass },
+            black: if black_is_legal { mov_pair.black } else { Move::Pass }
+        });
+        let penalised: Vec<Colour> = [(white_is_legal, Colour::White), (black_is_legal, Colour::Black)]
+            .into_iter()
+            .filter(|(is_legal, _)| !is_legal)
+            .map(|(_, colour)| colour)
+            .collect();
+        for colour in penalised.iter() {
+            match colour {
+                Colour::White => penalty_points.white += 1,
+                Colour::Black => penalty_points.black += 1
+            }
+        }
+        self.penalty_points = Some(penalty_points);
+        penalised
+    }
+    fn execute_single_move(&mut self, mov: PieceMove) {
+        let piece = self.board[mov.from_square as usize];
+        self.set_square(mov.from_square, SquareVal::Empty);
+        self.set_square(mov.to_square, piece);
+    }
+    fn execute_moves_to_different_squares(&mut self, white: PieceMove, black: PieceMove) {
+        let white_piece = self.board[white.from_square as usize];
+        let black_piece = self.board[black.from_square as usize]; 
+        self.set_square(white.from_square, SquareVal::Empty);
+        self.set_square(black.from_square, SquareVal::Empty);
+        self.set_square(white.to_square, white_piece);
+        self.set_square(black.to_square, black_piece);
+    }
+    fn execute_moves_to_same_square(&mut self, white: PieceMove, black: PieceMove) {
+        let SquareVal::Piece(white_piece) = self.board[white.from_square as usize] else { panic!() };
+        let SquareVal::Piece(black_piece) = self.board[black.from_square as usize] else { panic!() }; 
+        if white_piece.kind == black_piece.kind {
+            self.set_square(white.from_square, SquareVal::Empty);
+            self.set_square(black.from_square, SquareVal::Empty);
+            self.set_square(white.to_square, SquareVal::Empty);
+        } else {
+            let winner = if white_piece.kind == Kind::Knight { white_piece } else { black_piece };
+            self.set_square(white.from_square, SquareVal::Empty);
+            self.set_square(black.from_square, SquareVal::Empty);
+            self.set_square(white.to_square, SquareVal::Piece(winner));
+        }
+    }
+    fn promote_pawns(&mut self) {
+        for colour in [Colour::White, Colour::Black] {
+            for square in bitboard::get_mailbox_indices(self.get_waiting_pawns(&colour)) {
+                if self.get_knight_count(&colour) < MAX_KNIGHTS {
+                    self.set_square(square, SquareVal::Piece(Piece { colour, kind: Kind::Knight }));
+                }
+            }
+        }
+    }
+    // Pawns on their last rank, which are only ever there waiting to be redeployed
+    fn get_waiting_pawns(&self, colour: &Colour) -> u32 {
+        self.bitboards.get_pieces(colour, &Kind::Pawn) & bitboard::get_last_rank(colour)
+    }
+    pub fn is_redeploying(&self) -> bool {
+        self.get_waiting_pawns(&Colour::White) != 0 || self.get_waiting_pawns(&Colour::Black) != 0
+    }
+    // A side that has lost all its footmen has lost, even though that usually leaves it with no moves too
+    pub fn get_game_state(&self) -> GameState {
+        if let Some(penalty_state) = self.try_get_penalty_state() {
+            penalty_state
+        } else if self.both_sides_no_footmen() {
+            GameState::Draw(DrawReason::MutualAnnihilation)
+        } else if let Some(colour) = self.try_get_winner() {
+            GameState::Win(colour, WinReason::NoFootmen)
+        } else if self.is_stalemate() {
+            GameState::Draw(DrawReason::Stalemate)
+        } else {
+            GameState::Ongoing
+        }
+    }
+    // Both sides reaching the limit with the same move pair is a draw
+    fn try_get_penalty_state(&self) -> Option<GameState> {
+        let penalty_points = self.penalty_points?;
+        match (penalty_points.white >= MAX_PENALTY_POINTS, penalty_points.black >= MAX_PENALTY_POINTS) {
+            (true, true) => Some(GameState::Draw(DrawReason::PenaltyPoints)),
+            (true, false) => Some(GameState::Win(Colour::Black, WinReason::PenaltyPoints)),
+            (false, true) => Some(GameState::Win(Colour::White, WinReason::PenaltyPoints)),
+            (false, false) => None
+        }
+    }
+    fn is_stalemate(&self) -> bool {
+        if self.is_redeploying() {
+            return false;
+        }
+        let white_has_no_moves = self.has_no_piece_moves(&Colour::White);
+        let black_has_no_moves = self.has_no_piece_moves(&Colour::Black);
+        match self.pass_rule {
+            PassRule::Never => white_has_no_moves || black_has_no_moves,
+            PassRule::WhenBlocked | PassRule::Always => white_has_no_moves && black_has_no_moves
+        }
+    }
+    fn both_sides_no_footmen(&self) -> bool {
+        self.has_no_footmen(Colour::White) && self.has_no_footmen(Colour::Black)
+    }
+    fn has_no_footmen(&self, colour: Colour) -> bool {
+        self.bitboards.get_pieces(&colour, &Kind::Pawn) == 0
+    }
+    pub fn has_no_piece_moves(&self, colour: &Colour) -> bool {
+        self.get_all_piece_moves(*colour).is_empty()
+    }
+    fn get_knight_count(&self, colour: &Colour) -> usize {
+        self.bitboards.get_pieces(colour, &Kind::Knight).count_ones() as usize
+    }
+    fn try_get_winner(&self) -> Option<Colour> {
+        if self.has_no_footmen(Colour::White) {
+            Some(Colour::Black)
+        } else if self.has_no_footmen(Colour::Black) {
+            Some(Colour::White) 
+        } else {
+            None
+        }
+    }
+    pub fn is_legal_move(&self, mov: &Move, colour: &Colour) -> bool {
+        match mov {
+            Move::Pass => self.can_pass(self.has_no_piece_moves(colour)),
+            Move::Piece(mov) if self.is_redeploying() => self.is_legal_redeploy(mov, colour),
+            Move::Piece(mov) => self.is_legal_movement(mov, colour)
+        }
+    }
+    fn is_legal_movement(&self, mov: &PieceMove, colour: &Colour) -> bool {
+        let SquareVal::Piece(piece) = self.board[mov.from_square as usize] else {
+            return false;
+        };
+        if is_invalid_movement(mov, &piece) {
+            return false;
+        };
+        match piece.kind {
+            Kind::Knight => self.is_legal_knight_move(mov, colour),
+            Kind::Pawn => {
+                let mov_type = get_move_type(mov);
+                self.is_legal_pawn_move(mov, &mov_type, colour)
+            }
+        }
+         
+    }
+    fn is_legal_redeploy(&self, mov: &PieceMove, colour: &Colour) -> bool {
+        self.get_redeploy_moves(colour)
+            .iter()
+            .any(|redeploy| redeploy.from_square == mov.from_square && redeploy.to_square == mov.to_square)
+    }
+    fn get_boards_possible_for_move(&self, our_move: &Move, our_colour: &Colour, all_opponent_moves: &[Move]) -> Vec<Board> { 
+        all_opponent_moves
+            .iter()
+            .map(|opp_move| {
+                let white_move = match *our_colour {
+                    Colour::White => our_move,
+                    Colour::Black => opp_move
+                };
+                let black_move = match *our_colour {
+                    Colour::White => opp_move,
+                    Colour::Black => our_move
+                };
+                let mov_pair = MovePair {
+                    white: *white_move,
+                    black: *black_move
+                };
+                let mut new_board = *self;
+                new_board.execute_moves(mov_pair);
+                new_board
+            })
+            .collect()                
+    }
+} 
+
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
+}
+
+impl fmt::Display for Colour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Colour::White => write!(f, "White"),
+            Colour::Black => write!(f, "Black")
+        }
+    }
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawReason::MutualAnnihilation => write!(f, "both sides lost their last footmen"),
+            DrawReason::Stalemate => write!(f, "a side has no moves"),
+            DrawReason::PenaltyPoints => write!(f, "both sides reached the penalty point limit"),
+            DrawReason::Repetition => write!(f, "the same position came up three times"),
+            DrawReason::NoProgress => write!(f, "no captures or pawn moves for too long"),
+            DrawReason::MoveLimit => write!(f, "the move pair limit was reached")
+        }
+    }
+}
+
+impl fmt::Display for WinReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WinReason::NoFootmen => write!(f, "by taking every footman"),
+            WinReason::PenaltyPoints => write!(f, "on penalty points"),
+            WinReason::Resignation => write!(f, "by resignation"),
+            WinReason::Timeout => write!(f, "on time")
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::Pass => write!(f, "pass"),
+            Move::Piece(mov) => write!(f, "{}", mov)
+        }
+    }
+}
+
+impl fmt::Display for PieceMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", get_algebraic_square(self.from_square), get_algebraic_square(self.to_square))
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                
+        let board_height = 5;
+        let board_width = 5;
+
+        for row in 0..board_height {
+            for col in 0..board_width {
+                let one_d_coordinate = grid_to_one_d(row, col);
+                let square_val = self.board[one_d_coordinate];
+                write!(f, "{}", square_val).unwrap();
+            }
+            writeln!(f).unwrap();
+        }
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for SquareVal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SquareVal::Piece(piece) => write!(f, "{}", piece),
+            SquareVal::Empty => write!(f, " "),
+            SquareVal::Invalid => write!(f, "")
+        }
+    }
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.colour {
+            Colour::White => match self.kind {
+                Kind::Knight => write!(f, "♞"),
+                Kind::Pawn => write!(f, "♟")
+            },
+            Colour::Black => match self.kind {
+                Kind::Knight => write!(f, "♘"),
+                Kind::Pawn => write!(f, "♙")
+            }
+        }
+    }
+}
+    
+impl Neg for Colour {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        match self {
+            Colour::Black => Colour::White,
+            Colour::White => Colour::Black
+        }
+    }
+}
+
+fn is_invalid_movement(mov: &PieceMove, piece: &Piece) -> bool {
+    match piece.kind {
+        Kind::Knight => is_invalid_knight_movement(mov),
+        Kind::Pawn => is_invalid_pawn_movement(mov, &piece.colour)
+    }
+}
+
+fn is_invalid_knight_movement(mov: &PieceMove) -> bool {
+    ![13, 15, -13, -15, 5, 9, -5, -9].contains(&get_movement(mov))
+}
+
+fn is_invalid_pawn_movement(mov: &PieceMove, colour: &Colour) -> bool {
+    let forward = get_pawn_direction(colour) as isize;
+    ![forward - 1, forward, forward + 1].contains(&get_movement(mov))
+}
+
+fn get_movement(mov: &PieceMove) -> isize {
+    mov.to_square as isize - mov.from_square as isize
+}
+
+fn get_pawn_direction(colour: &Colour) -> i8 {
+    // White starts at the bottom of the board and moves up it, Black starts at the top and moves down it
+    match colour {
+        Colour::White => -7,
+        Colour::Black => 7
+    }
+}
+
+fn get_move_type(mov: &PieceMove) -> MoveType {
+    if get_movement(mov).abs() == 7 {
+        MoveType::MoveOnly
+    } else {
+        MoveType::Attack
+    }
+}
+
+fn grid_to_one_d(row: usize, col: usize) -> usize {
+    15 + (7 * row) + (col)
+}
+
+// Whether the two lists have the same moves, whatever order they're in
+fn have_same_moves(moves: &[PieceMove], other_moves: &[PieceMove]) -> bool {
+    let get_sorted_keys = |moves: &[PieceMove]| {
+        let mut keys: Vec<(u8, u8)> = moves.iter().map(|mov| (mov.from_square, mov.to_square)).collect();
+        keys.sort_unstable();
+        keys
+    };
+    get_sorted_keys(moves) == get_sorted_keys(other_moves)
+}
+
+fn is_right_colour_footman(square: &SquareVal, colour: &Colour) -> bool {
+    match square {
+        SquareVal::Piece(piece) => piece.kind == Kind::Pawn && piece.colour == *colour,
+        _ => false
+    }
+}
+
+pub fn try_get_u8_from_algebraic(s: String) -> Option<u8> {
+    let mut chars = s.chars();
+    let row_val = try_get_row(chars.next()?)?;
+    let col_val = try_get_col(chars.next()?)?;
+    Some(row_val - (7 * (col_val - 1)))
+}
+
+pub fn get_algebraic_square(index: u8) -> String {
+    let file = (b'a' + (index % 7) - 1) as char;
+    let rank = 7 - (index / 7);
+    format!("{}{}", file, rank)
+}
+
+fn try_get_row(row: char) -> Option<u8> {
+    match row.to_string().to_lowercase().as_str() {
+        "a" => Some(43),
+        "b" => Some(44),
+        "c" => Some(45),
+        "d" => Some(46),
+        "e" => Some(47),
+        _ => None
+    }
+}
+
+fn try_get_col(col: char) -> Option<u8> {
+    match col.to_string().parse::<u8>() {
+        Ok(num) if (1..=5).contains(&num) => Some(num),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays a move pair on a board written as in a dataset, with a space allowed between ranks, and returns the board
+    // it leaves in the same format. Either side may pass.
+    fn get_board_after(board: &str, white: &str, black: &str) -> String {
+        let mut board = dataset::parse_board(&board.replace(' ', "")).unwrap();
+        board.pass_rule = PassRule::Always;
+        let mov_pair = MovePair { white: parse_move(white), black: parse_move(black) };
+        assert!(board.is_legal_move(&mov_pair.white, &Colour::White), "{} isn't legal for White", white);
+        assert!(board.is_legal_move(&mov_pair.black, &Colour::Black), "{} isn't legal for Black", black);
+        board.execute_moves(mov_pair);
+        assert_eq!(board.hash, zobrist::hash_squares(&board.board));
+        let squares = dataset::format_board(&board);
+        (0..5).map(|rank| &squares[rank * 5..(rank + 1) * 5]).collect::<Vec<&str>>().join(" ")
+    }
+
+    // Moves are written as they're displayed, e.g. a4-a5, or pass
+    fn parse_move(text: &str) -> Move {
+        if text == "pass" {
+            return Move::Pass;
+        }
+        let (from, to) = text.split_once('-').unwrap();
+        Move::Piece(PieceMove {
+            from_square: try_get_u8_from_algebraic(from.to_string()).unwrap(),
+            to_square: try_get_u8_from_algebraic(to.to_string()).unwrap()
+        })
+    }
+
+    #[test]
+    fn moves_to_different_squares_are_both_made() {
+        assert_eq!(get_board_after("..p.. ..... ..... ..... .P...", "b1-b2", "c5-c4"), "..... ..p.. ..... .P... .....");
+    }
+
+    #[test]
+    fn piece_that_stays_is_captured() {
+        assert_eq!(get_board_after("....p ..... ..p.. ..... .N..P", "b1-c3", "e5-e4"), "..... ....p ..N.. ..... ....P");
+    }
+
+    #[test]
+    fn piece_that_moves_away_escapes_capture() {
+        assert_eq!(get_board_after("..... ..... ..p.. ..... .N..P", "b1-c3", "c3-c2"), "..... ..... ..N.. ..p.. ....P");
+    }
+
+    #[test]
+    fn pawn_still_moves_diagonally_when_its_target_moves_away() {
+        assert_eq!(get_board_after("..... ..... ..p.. .P... .....", "b2-c3", "c3-c2"), "..... ..... ..P.. ..p.. .....");
+    }
+
+    #[test]
+    fn side_that_passes_stands_still_while_the_other_side_moves() {
+        assert_eq!(get_board_after("..p.. ..... ..... ..... .P...", "pass", "c5-c4"), "..... ..p.. ..... ..... .P...");
+    }
+
+    #[test]
+    fn piece_of_a_side_that_passes_can_be_captured() {
+        assert_eq!(get_board_after("..... ..... ..p.. ..... .N...", "b1-c3", "pass"), "..... ..... ..N.. ..... .....");
+    }
+
+    #[test]
+    fn pieces_capturing_each_other_swap_squares() {
+        assert_eq!(get_board_after("..... ..... ..p.. .P... .....", "b2-c3", "c3-b2"), "..... ..... ..P.. .p... .....");
+    }
+
+    #[test]
+    fn piece_moving_away_can_capture_a_piece_that_stays() {
+        assert_eq!(get_board_after("..... ..... ..p.. .P... .N...", "b1-c3", "c3-b2"), "..... ..... ..N.. .p... .....");
+    }
+
+    #[test]
+    fn pieces_of_the_same_kind_moving_to_the_same_square_are_both_removed() {
+        assert_eq!(get_board_after("....p .p... ..... .P... ....P", "b2-b3", "b4-b3"), "....p ..... ..... ..... ....P");
+        assert_eq!(get_board_after("n...p ..... ..... ..... N...P", "a1-b3", "a5-b3"), "....p ..... ..... ..... ....P");
+    }
+
+    #[test]
+    fn knight_beats_pawn_moving_to_the_same_square() {
+        assert_eq!(get_board_after("....p .p... ..... ..... N...P", "a1-b3", "b4-b3"), "....p ..... .N... ..... ....P");
+        assert_eq!(get_board_after("n...p ..... ..... .P... ....P", "b2-b3", "a5-b3"), "....p ..... .n... ..... ....P");
+    }
+
+    #[test]
+    fn pawns_promote_on_the_last_rank() {
+        assert_eq!(get_board_after("..... ..P.. ..... ..p.. .....", "c4-c5", "c2-c1"), "..N.. ..... ..... ..... ..n..");
+    }
+
+    #[test]
+    fn pawns_promote_while_their_side_has_fewer_than_two_knights() {
+        assert_eq!(get_board_after("n.... ..P.. ..... ..p.. N....", "c4-c5", "c2-c1"), "n.N.. ..... ..... ..... N.n..");
+    }
+
+    #[test]
+    fn pawn_reaching_the_last_rank_with_two_knights_waits_there() {
+        assert_eq!(get_board_after("n...n ..P.. ..... ..p.. N...N", "c4-c5", "c2-c1"), "n.P.n ..... ..... ..... N.p.N");
+    }
+
+    #[test]
+    fn waiting_pawns_are_redeployed_with_the_next_move_pair() {
+        assert_eq!(get_board_after("n.P.n ..... ..... ..... N.p.N", "c5-a3", "c1-e3"), "n...n ..... P...p ..... N...N");
+    }
+
+    #[test]
+    fn waiting_pawn_can_go_to_any_square_empty_once_the_moves_are_made() {
+        let mut board = dataset::parse_board(&"n...n ..P.p .n... ..... N...N".replace(' ', "")).unwrap();
+        board.execute_moves(MovePair { white: parse_move("c4-c5"), black: parse_move("b3-d2") });
+        // Where the pawn came from and where Black's knight left
+        assert!(board.is_legal_move(&parse_move("c5-c4"), &Colour::White));
+        assert!(board.is_legal_move(&parse_move("c5-b3"), &Colour::White));
+        assert!(!board.is_legal_move(&parse_move("c5-d2"), &Colour::White));
+        assert!(!board.is_legal_move(&parse_move("c5-d5"), &Colour::White));
+        assert!(!board.is_legal_move(&Move::Pass, &Colour::White));
+        // Every square off rank 5 except the four taken
+        assert_eq!(board.get_all_legal_moves(Colour::White).len(), 16);
+    }
+
+    #[test]
+    fn other_side_waits_while_a_pawn_is_redeployed() {
+        let mut board = dataset::parse_board("n.P.n....p..........N...N").unwrap();
+        assert!(matches!(board.get_all_legal_moves(Colour::Black)[..], [Move::Pass]));
+        assert!(matches!(board.get_game_state(), GameState::Ongoing));
+        board.execute_moves(MovePair { white: parse_move("c5-c3"), black: Move::Pass });
+        assert_eq!(dataset::format_board(&board), "n...n....p..P.......N...N");
+        assert!(!board.is_legal_move(&Move::Pass, &Colour::Black));
+        assert!(board.is_legal_move(&parse_move("e4-e3"), &Colour::Black));
+    }
+
+    #[test]
+    fn side_that_loses_its_last_footman_loses() {
+        let board = dataset::parse_board("n...p ..... ..... ..... N....".replace(' ', "").as_str()).unwrap();
+        assert!(matches!(board.get_game_state(), GameState::Win(Colour::Black, WinReason::NoFootmen)));
+    }
+
+    #[test]
+    fn side_whose_last_piece_is_captured_loses() {
+        let board = dataset::parse_board("....p ..... ..... ..... .....".replace(' ', "").as_str()).unwrap();
+        assert!(matches!(board.get_game_state(), GameState::Win(Colour::Black, WinReason::NoFootmen)));
+        let mut board = dataset::parse_board("..... ..... p.... ..... .N..P".replace(' ', "").as_str()).unwrap();
+        board.pass_rule = PassRule::Always;
+        board.execute_moves(MovePair { white: parse_move("b1-a3"), black: Move::Pass });
+        assert!(matches!(board.get_game_state(), GameState::Win(Colour::White, WinReason::NoFootmen)));
+    }
+
+    #[test]
+    fn both_sides_losing_their_last_footmen_together_is_a_draw() {
+        let board = dataset::parse_board("n.... ..... ..... ..... N....".replace(' ', "").as_str()).unwrap();
+        assert!(matches!(board.get_game_state(), GameState::Draw(DrawReason::MutualAnnihilation)));
+    }
+
+    #[test]
+    fn side_with_no_moves_is_a_draw() {
+        let board = dataset::parse_board("..... ..p.. ..P.. ..... N....".replace(' ', "").as_str()).unwrap();
+        assert!(matches!(board.get_game_state(), GameState::Draw(DrawReason::Stalemate)));
+    }
+
+    #[test]
+    fn white_with_no_moves_is_a_draw() {
+        let board = dataset::parse_board("n.... ..p.. ..P.. ..... .....".replace(' ', "").as_str()).unwrap();
+        assert!(matches!(board.get_game_state(), GameState::Draw(DrawReason::Stalemate)));
+    }
+
+    #[test]
+    fn side_with_no_moves_passes_when_passing_is_allowed_once_blocked() {
+        let mut board = dataset::parse_board("n.... ..p.. ..P.. ..... .....".replace(' ', "").as_str()).unwrap();
+        board.pass_rule = PassRule::WhenBlocked;
+        assert!(matches!(board.get_game_state(), GameState::Ongoing));
+        assert!(matches!(board.get_all_legal_moves(Colour::White)[..], [Move::Pass]));
+        assert!(!board.is_legal_move(&Move::Pass, &Colour::Black));
+        board.execute_moves(MovePair { white: Move::Pass, black: parse_move("a5-b3") });
+        assert_eq!(dataset::format_board(&board), ".......p...nP............");
+    }
+
+    #[test]
+    fn neither_side_having_moves_is_a_draw_when_passing_is_allowed() {
+        let mut board = dataset::parse_board("..... ..p.. ..P.. ..... .....".replace(' ', "").as_str()).unwrap();
+        board.pass_rule = PassRule::Always;
+        assert!(matches!(board.get_game_state(), GameState::Draw(DrawReason::Stalemate)));
+    }
+
+    #[test]
+    fn passing_is_only_legal_when_the_rule_allows_it() {
+        let mut board = Board::new();
+        assert!(!board.is_legal_move(&Move::Pass, &Colour::White));
+        board.pass_rule = PassRule::WhenBlocked;
+        assert!(!board.is_legal_move(&Move::Pass, &Colour::White));
+        board.pass_rule = PassRule::Always;
+        assert!(board.is_legal_move(&Move::Pass, &Colour::White));
+        assert!(board.get_all_legal_moves(Colour::Black).iter().any(|mov| matches!(mov, Move::Pass)));
+    }
+
+    #[test]
+    fn game_with_moves_and_footmen_on_both_sides_goes_on() {
+        assert!(matches!(Board::new().get_game_state(), GameState::Ongoing));
+    }
+
+    #[test]
+    fn illegal_move_gets_a_penalty_point_and_the_other_move_is_still_played() {
+        let mut board = dataset::parse_board("..p.....................P").unwrap();
+        board.penalty_points = Some(PenaltyPoints::default());
+        let penalised = board.play_submitted_moves(MovePair { white: parse_move("e1-e3"), black: parse_move("c5-c4") });
+        assert!(matches!(penalised[..], [Colour::White]));
+        assert_eq!(dataset::format_board(&board), ".......p................P");
+        assert!(matches!(board.penalty_points, Some(PenaltyPoints { white: 1, black: 0 })));
+        assert!(matches!(board.get_game_state(), GameState::Ongoing));
+    }
+
+    #[test]
+    fn side_with_two_penalty_points_loses() {
+        let mut board = dataset::parse_board("..p.....................P").unwrap();
+        board.penalty_points = Some(PenaltyPoints { white: 1, black: 0 });
+        board.play_submitted_moves(MovePair { white: parse_move("e1-e3"), black: parse_move("c5-c4") });
+        assert!(matches!(board.get_game_state(), GameState::Win(Colour::Black, WinReason::PenaltyPoints)));
+    }
+
+    #[test]
+    fn both_sides_reaching_two_penalty_points_together_is_a_draw() {
+        let mut board = dataset::parse_board("..p.....................P").unwrap();
+        board.penalty_points = Some(PenaltyPoints { white: 1, black: 1 });
+        let penalised = board.play_submitted_moves(MovePair { white: parse_move("e1-e3"), black: parse_move("c5-c3") });
+        assert_eq!(penalised.len(), 2);
+        assert_eq!(dataset::format_board(&board), "..p.....................P");
+        assert!(matches!(board.get_game_state(), GameState::Draw(DrawReason::PenaltyPoints)));
+    }
+
+    #[test]
+    fn pawns_redeployed_to_the_same_square_collide() {
+        assert_eq!(get_board_after("n.P.n p.... ..... ....P N.p.N", "c5-c3", "c1-c3"), "n...n p.... ..... ....P N...N");
+    }
+}
@@ -0,0 +1,160 @@
+/*
+    Solver for two player zero-sum matrix games.
+
+    payoffs[i][j] is the value to the row player when they play i and the column player plays j.
+    The row player maximises and the column player minimises. Both players' optimal mixed strategies come out of
+    one linear program and its dual, so moves that are dominated or never played at equilibrium just get probability 0.
+*/
+
+use crate::simplex::{self, Constraint, Relation};
+
+// How far each strategy may fall short of the value against the other side's moves, relative to the payoff range
+const EQUILIBRIUM_TOLERANCE: f64 = 1e-6;
+
+pub struct GameSolution {
+    pub row_strategy: Vec<f64>,
+    pub column_strategy: Vec<f64>,
+    pub value: f64
+}
+
+pub fn solve_game(payoffs: &[Vec<f64>]) -> Option<GameSolution> {
+    let num_columns = payoffs.first()?.len();
+    if num_columns == 0 || payoffs.iter().any(|row| row.len() != num_columns) {
+        return None;
+    }
+    // Rescale the payoffs to lie between 1 and 2. The formulation below needs every payoff to be positive,
+    // and the LP doesn't lose precision when the payoffs mix win values with small evaluations.
+    let min_payoff = payoffs
+        .iter()
+        .flatten()
+        .cloned()
+        .fold(f64::INFINITY, f64::min);
+    let max_payoff = payoffs
+        .iter()
+        .flatten()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let range = (max_payoff - min_payoff).max(f64::MIN_POSITIVE);
+    let scaled: Vec<Vec<f64>> = payoffs
+        .iter()
+        .map(|row| row.iter().map(|payoff| 1.0 + (payoff - min_payoff) / range).collect())
+        .collect();
+    // Classic formulation with no phase one needed: maximise SUM y/j s.t. SUM y/j * payoff/i,j <= 1 for every row i.
+    // The optimum is 1 / value, y normalised is the column player's strategy and the duals normalised are the row player's.
+    let objective = vec![1.0; num_columns];
+    let constraints: Vec<Constraint> = scaled
+        .iter()
+        .map(|row| Constraint { coefficients: row.clone(), relation: Relation::LessOrEqual, rhs: 1.0 })
+        .collect();
+    let solution = simplex::maximise(&objective, &constraints)?;
+    let game_solution = GameSolution {
+        row_strategy: normalise(&solution.duals),
+        column_strategy: normalise(&solution.variables),
+        value: min_payoff + (1.0 / solution.objective - 1.0) * range
+    };
+    debug_assert!(is_equilibrium(payoffs, &game_solution, range), "The strategies found don't hold the game to its value");
+    Some(game_solution)
+}
+
+// Whether the row strategy gets at least the value against every column, and the column strategy gives away at most
+// the value against every row
+fn is_equilibrium(payoffs: &[Vec<f64>], solution: &GameSolution, range: f64) -> bool {
+    let tolerance = EQUILIBRIUM_TOLERANCE * range.max(1.0);
+    let row_holds = (0..solution.column_strategy.len()).all(|col| {
+        let payoff: f64 = payoffs.iter().zip(solution.row_strategy.iter()).map(|(row, prob)| row[col] * prob).sum();
+        payoff >= solution.value - tolerance
+    });
+    let column_holds = payoffs.iter().all(|row| {
+        let payoff: f64 = row.iter().zip(solution.column_strategy.iter()).map(|(payoff, prob)| payoff * prob).sum();
+        payoff <= solution.value + tolerance
+    });
+    row_holds && column_holds
+}
+
+fn normalise(probabilities: &[f64]) -> Vec<f64> {
+    // Clean up the small rounding errors the simplex leaves behind
+    let clamped: Vec<f64> = probabilities.iter().map(|prob| prob.max(0.0)).collect();
+    let total: f64 = clamped.iter().sum();
+    clamped.iter().map(|prob| prob / total).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual_prob, expected_prob) in actual.iter().zip(expected.iter()) {
+            assert!((actual_prob - expected_prob).abs() < 1e-9, "{:?} isn't {:?}", actual, expected);
+        }
+    }
+
+    fn assert_solution(payoffs: &[Vec<f64>], row_strategy: &[f64], column_strategy: &[f64], value: f64) {
+        let solution = solve_game(payoffs).unwrap();
+        assert_close(&solution.row_strategy, row_strategy);
+        assert_close(&solution.column_strategy, column_strategy);
+        assert_close(&[solution.value], &[value]);
+    }
+
+    #[test]
+    fn single_row_picks_the_lowest_column() {
+        assert_solution(&[vec![3.0, 1.0, 2.0]], &[1.0], &[0.0, 1.0, 0.0], 1.0);
+    }
+
+    #[test]
+    fn single_column_picks_the_highest_row() {
+        assert_solution(&[vec![3.0], vec![1.0], vec![2.0]], &[1.0, 0.0, 0.0], &[1.0], 3.0);
+    }
+
+    #[test]
+    fn single_cell_is_its_own_value() {
+        assert_solution(&[vec![5.0]], &[1.0], &[1.0], 5.0);
+    }
+
+    #[test]
+    fn constant_game_is_worth_the_constant_whatever_is_played() {
+        let solution = solve_game(&[vec![2.0, 2.0], vec![2.0, 2.0]]).unwrap();
+        assert_close(&[solution.value], &[2.0]);
+        assert_close(&[solution.row_strategy.iter().sum()], &[1.0]);
+        assert_close(&[solution.column_strategy.iter().sum()], &[1.0]);
+    }
+
+    #[test]
+    fn matching_pennies_is_played_half_and_half() {
+        assert_solution(&[vec![1.0, -1.0], vec![-1.0, 1.0]], &[0.5, 0.5], &[0.5, 0.5], 0.0);
+    }
+
+    #[test]
+    fn rock_paper_scissors_is_played_in_thirds() {
+        let third = 1.0 / 3.0;
+        let payoffs = [vec![0.0, -1.0, 1.0], vec![1.0, 0.0, -1.0], vec![-1.0, 1.0, 0.0]];
+        assert_solution(&payoffs, &[third; 3], &[third; 3], 0.0);
+    }
+
+    #[test]
+    fn dominated_row_is_never_played() {
+        // The middle row is worse than the top one whatever the column player does
+        assert_solution(&[vec![3.0, 1.0], vec![2.0, 0.0], vec![0.0, 2.0]], &[0.5, 0.0, 0.5], &[0.25, 0.75], 1.5);
+    }
+
+    #[test]
+    fn duplicate_rows_share_their_probability() {
+        let solution = solve_game(&[vec![1.0, -1.0], vec![1.0, -1.0], vec![-1.0, 1.0]]).unwrap();
+        assert_close(&[solution.row_strategy[0] + solution.row_strategy[1], solution.row_strategy[2]], &[0.5, 0.5]);
+        assert_close(&solution.column_strategy, &[0.5, 0.5]);
+        assert_close(&[solution.value], &[0.0]);
+    }
+
+    #[test]
+    fn small_evaluations_survive_next_to_win_values() {
+        assert_solution(&[vec![1000.0, -1000.0], vec![0.5, 0.2]], &[0.0, 1.0], &[0.0, 1.0], 0.2);
+        assert_solution(&[vec![1000.0, -1000.0], vec![-1000.0, 1000.0]], &[0.5, 0.5], &[0.5, 0.5], 0.0);
+    }
+
+    #[test]
+    fn ragged_or_empty_payoffs_have_no_solution() {
+        assert!(solve_game(&[]).is_none());
+        assert!(solve_game(&[vec![]]).is_none());
+        assert!(solve_game(&[vec![1.0, 2.0], vec![1.0]]).is_none());
+    }
+}
@@ -0,0 +1,256 @@
+/*
+    Monte Carlo tree search for the simultaneous move game, with decoupled selection.
+
+    At every node each player keeps statistics for their own moves only and picks their move without looking at
+    the other's choice (decoupled UCT), using either UCB1 or Exp3. The pair of moves picked decides which child to
+    descend into. New leaves are valued by playing random moves until the game ends.
+
+    Rewards are from our side's point of view: 1 for a win, 0.5 for a draw and 0 for a loss, so our opponent's
+    reward for the same result is 1 minus ours.
+*/
+
+use std::collections::HashMap;
+use rand::{seq::SliceRandom, Rng};
+use crate::{search::SearchResult, Board, Colour, GameState, Move, MovePair};
+
+// Playouts that haven't finished after this many move pairs are scored as a draw, as the game may never end
+const MAX_PLAYOUT_MOVE_PAIRS: u32 = 200;
+
+#[derive(Clone, Copy)]
+pub enum Selection {
+    Ucb,
+    Exp3
+}
+
+#[derive(Clone, Copy)]
+pub struct MctsSettings {
+    pub iterations: u32,
+    // The UCB exploration constant, or gamma (the share of moves picked uniformly at random) for Exp3
+    pub exploration: f64,
+    pub selection: Selection
+}
+
+struct Node {
+    board: Board,
+    our_moves: Vec<Move>,
+    opponent_moves: Vec<Move>,
+    our_stats: Vec<MoveStats>,
+    opponent_stats: Vec<MoveStats>,
+    children: HashMap<(usize, usize), Node>,
+    visits: u32,
+    terminal_reward: Option<f64>
+}
+
+#[derive(Clone, Default)]
+struct MoveStats {
+    visits: u32,
+    total_reward: f64,
+    // Exp3's importance weighted estimate of the total reward this move would have got
+    estimated_reward: f64
+}
+
+pub fn search<R: Rng>(board: &Board, colour: Colour, settings: &MctsSettings, rng: &mut R) -> Option<SearchResult> {
+    let mut root = Node::new(*board, colour);
+    if root.terminal_reward.is_some() {
+        return None;
+    }
+    for _ in 0..settings.iterations {
+        root.run_iteration(colour, settings, rng);
+    }
+    // The share of visits each move got is our mixed strategy
+    let strategy = root.our_stats
+        .iter()
+        .map(|stats| stats.visits as f64 / root.visits as f64)
+        .collect();
+    Some(SearchResult {
+        moves: root.our_moves,
+        strategy
+    })
+}
+
+impl Node {
+    fn new(board: Board, colour: Colour) -> Node {
+        let our_moves = board.get_all_legal_moves(colour);
+        let opponent_moves = board.get_all_legal_moves(-colour);
+        Node {
+            terminal_reward: get_terminal_reward(&board, colour, &our_moves, &opponent_moves),
+            our_stats: vec![MoveStats::default(); our_moves.len()],
+            opponent_stats: vec![MoveStats::default(); opponent_moves.len()],
+            children: HashMap::new(),
+            visits: 0,
+            board,
+            our_moves,
+            opponent_moves
+        }
+    }
+    fn run_iteration<R: Rng>(&mut self, colour: Colour, settings: &MctsSettings, rng: &mut R) -> f64 {
+        if let Some(reward) = self.terminal_reward {
+            return reward;
+        }
+        let (our_index, our_prob) = select_move(&self.our_stats, self.visits, settings, rng);
+        let (opponent_index, opponent_prob) = select_move(&self.opponent_stats, self.visits, settings, rng);
+        let reward = match self.children.get_mut(&(our_index, opponent_index)) {
+            Some(child) => child.run_iteration(colour, settings, rng),
+            None => {
+                let child_board = self.board.get_boards_possible_for_move(
+                    &self.our_moves[our_index],
+                    &colour,
+                    &[self.opponent_moves[opponent_index]]
+                )[0];
+                let child = Node::new(child_board, colour);
+                let reward = child.terminal_reward.unwrap_or_else(|| playout(&child_board, colour, rng));
+                self.children.insert((our_index, opponent_index), child);
+                reward
+            }
+        };
+        self.our_stats[our_index].update(reward, our_prob);
+        self.opponent_stats[opponent_index].update(1.0 - reward, opponent_prob);
+        self.visits += 1;
+        reward
+    }
+}
+
+impl MoveStats {
+    fn update(&mut self, reward: f64, prob: f64) {
+        self.visits += 1;
+        self.total_reward += reward;
+        self.estimated_reward += reward / prob;
+    }
+}
+
+// Returns the index of the move picked and the probability it had of being picked
+fn select_move<R: Rng>(stats: &[MoveStats], node_visits: u32, settings: &MctsSettings, rng: &mut R) -> (usize, f64) {
+    match settings.selection {
+        Selection::Ucb => (select_ucb_move(stats, node_visits, settings.exploration, rng), 1.0),
+        Selection::Exp3 => select_exp3_move(stats, settings.exploration, rng)
+    }
+}
+
+fn select_ucb_move<R: Rng>(stats: &[MoveStats], node_visits: u32, exploration: f64, rng: &mut R) -> usize {
+    let unvisited: Vec<usize> = (0..stats.len()).filter(|index| stats[*index].visits == 0).collect();
+    if let Some(index) = unvisited.choose(rng) {
+        return *index;
+    }
+    let log_visits = (node_visits as f64).ln();
+    let get_upper_bound = |stats: &MoveStats| {
+        let visits = stats.visits as f64;
+        stats.total_reward / visits + exploration * (log_visits / visits).sqrt()
+    };
+    (0..stats.len())
+        .max_by(|a, b| get_upper_bound(&stats[*a]).total_cmp(&get_upper_bound(&stats[*b])))
+        .unwrap_or(0)
+}
+
+fn select_exp3_move<R: Rng>(stats: &[MoveStats], gamma: f64, rng: &mut R) -> (usize, f64) {
+    let num_moves = stats.len() as f64;
+    let eta = gamma / num_moves;
+    // Subtracting the largest estimate doesn't change the probabilities but stops exp overflowing
+    let max_estimate = stats
+        .iter()
+        .map(|stats| stats.estimated_reward)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = stats
+        .iter()
+        .map(|stats| (eta * (stats.estimated_reward - max_estimate)).exp())
+        .collect();
+    let total_weight: f64 = weights.iter().sum();
+    let probabilities: Vec<f64> = weights
+        .iter()
+        .map(|weight| (1.0 - gamma) * weight / total_weight + gamma / num_moves)
+        .collect();
+    let indices: Vec<usize> = (0..stats.len()).collect();
+    let index = *indices
+        .choose_weighted(rng, |index| probabilities[*index])
+        .unwrap_or(&0);
+    (index, probabilities[index])
+}
+
+pub fn get_terminal_reward(board: &Board, colour: Colour, our_moves: &[Move], opponent_moves: &[Move]) -> Option<f64> {
+    match board.get_game_state() {
+        GameState::Win(winner, _) if winner == colour => Some(1.0),
+        GameState::Win(..) => Some(0.0),
+        GameState::Draw(_) => Some(0.5),
+        GameState::Ongoing if our_moves.is_empty() || opponent_moves.is_empty() => Some(0.5),
+        GameState::Ongoing => None
+    }
+}
+
+pub fn playout<R: Rng>(board: &Board, colour: Colour, rng: &mut R) -> f64 {
+    let mut board = *board;
+    for _ in 0..MAX_PLAYOUT_MOVE_PAIRS {
+        let white_moves = board.get_all_legal_moves(Colour::White);
+        let black_moves = board.get_all_legal_moves(Colour::Black);
+        let (our_moves, opponent_moves) = match colour {
+            Colour::White => (&white_moves, &black_moves),
+            Colour::Black => (&black_moves, &white_moves)
+        };
+        if let Some(reward) = get_terminal_reward(&board, colour, our_moves, opponent_moves) {
+            return reward;
+        }
+        let (Some(white), Some(black)) = (white_moves.choose(rng), black_moves.choose(rng)) else {
+            return 0.5;
+        };
+        board.execute_moves(MovePair { white: *white, black: *black });
+    }
+    0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use crate::dataset;
+
+    // Each selection rule with the exploration it gets by default
+    const SETTINGS: [MctsSettings; 2] = [
+        MctsSettings { iterations: 2000, exploration: std::f64::consts::SQRT_2, selection: Selection::Ucb },
+        MctsSettings { iterations: 2000, exploration: 0.2, selection: Selection::Exp3 }
+    ];
+
+    // Black's last pawn on c3 is stuck behind White's pawn, so b1-c3 takes it whatever Black does
+    fn get_forced_win() -> Board {
+        dataset::parse_board(&"n.... ..... ..p.. ..P.. .N...".replace(' ', "")).unwrap()
+    }
+
+    fn get_strategy(board: &Board, settings: &MctsSettings, seed: u64) -> Option<SearchResult> {
+        search(board, Colour::White, settings, &mut StdRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_strategy() {
+        for settings in SETTINGS.iter() {
+            let first = get_strategy(&Board::new(), &MctsSettings { iterations: 200, ..*settings }, 7).unwrap();
+            let second = get_strategy(&Board::new(), &MctsSettings { iterations: 200, ..*settings }, 7).unwrap();
+            assert_eq!(first.strategy, second.strategy);
+        }
+    }
+
+    #[test]
+    fn forced_win_gets_most_of_the_visits() {
+        for settings in SETTINGS.iter() {
+            let search_result = get_strategy(&get_forced_win(), settings, 0).unwrap();
+            let index = search_result.moves.iter().position(|mov| mov.to_string() == "b1-c3").unwrap();
+            assert!(search_result.strategy[index] > 0.5, "{:?}", search_result.strategy);
+        }
+    }
+
+    #[test]
+    fn strategy_sums_to_one() {
+        for settings in SETTINGS.iter() {
+            for board in [Board::new(), get_forced_win()] {
+                let search_result = get_strategy(&board, &MctsSettings { iterations: 500, ..*settings }, 3).unwrap();
+                assert_eq!(search_result.strategy.len(), search_result.moves.len());
+                assert!((search_result.strategy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn finished_game_has_nothing_to_search() {
+        // White has no footmen left
+        let board = dataset::parse_board(&"n...p ..... ..... ..... N....".replace(' ', "")).unwrap();
+        for settings in SETTINGS.iter() {
+            assert!(get_strategy(&board, settings, 0).is_none());
+        }
+    }
+}
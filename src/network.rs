@@ -0,0 +1,330 @@
+/*
+    An evaluator backed by a small neural network, and training it from labelled positions.
+
+    The network has an input for each of the 4 kinds of piece (white pawn, white knight, black pawn, black knight) on
+    each of the 25 squares, one hidden layer of ReLUs and a single output, which is the evaluation for White. It is
+    trained like the tuner fits the tables: the output is a logit, so sigmoid(output) is the predicted result for
+    White, and the weights are fitted by minimising the log loss against how each position's game ended.
+
+    Only the inputs for pieces on the board are ever 1, so the hidden layer is worked out by adding up the weights of
+    those inputs instead of multiplying through all 100 of them.
+
+    Networks are saved as TOML, with the hidden layer size and all the weights in one list: the input weights (all of
+    the hidden layer's weights for input 0 first), then the hidden biases, then the output weights and output bias.
+*/
+
+use std::fs;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::{dataset, evaluation::{Evaluator, Term}, grid_to_one_d, Board, Colour, Kind, SquareVal};
+
+const NUM_INPUTS: usize = 25 * 4;
+
+// Well inside the search's win values, so a network can never make a position look better than a win
+const MAX_VALUE: f64 = 100.0;
+
+const BATCH_SIZE: usize = 256;
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkEvaluator {
+    hidden_size: usize,
+    weights: Vec<f64>
+}
+
+pub struct TrainSettings {
+    pub dataset: String,
+    pub hidden_size: usize,
+    pub epochs: u32,
+    pub learning_rate: f64,
+    pub seed: u64,
+    pub output: Option<String>
+}
+
+struct Sample {
+    inputs: Vec<usize>,
+    result: f64
+}
+
+impl NetworkEvaluator {
+    fn new<R: Rng>(hidden_size: usize, rng: &mut R) -> NetworkEvaluator {
+        let mut network = NetworkEvaluator {
+            hidden_size,
+            weights: vec![0.0; (NUM_INPUTS + 2) * hidden_size + 1]
+        };
+        // Scaled so every hidden unit starts with roughly the same spread whatever the layer sizes
+        let input_range = (6.0 / (NUM_INPUTS + hidden_size) as f64).sqrt();
+        let output_range = (6.0 / (hidden_size + 1) as f64).sqrt();
+        for index in 0..NUM_INPUTS * hidden_size {
+            network.weights[index] = rng.gen_range(-input_range..input_range);
+        }
+        for index in network.get_output_weights_offset()..network.get_output_bias_index() {
+            network.weights[index] = rng.gen_range(-output_range..output_range);
+        }
+        network
+    }
+    pub fn load(path: &str) -> Result<NetworkEvaluator, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+        let network: NetworkEvaluator = toml::from_str(&contents).map_err(|err| format!("Couldn't parse {}: {}", path, err))?;
+        let expected_weights = (NUM_INPUTS + 2) * network.hidden_size + 1;
+        if network.weights.len() != expected_weights {
+            return Err(format!("{} has {} weights, a hidden layer of {} needs {}", path, network.weights.len(), network.hidden_size, expected_weights));
+        }
+        if network.weights.iter().any(|weight| !weight.is_finite()) {
+            return Err(format!("{} has weights that aren't numbers", path));
+        }
+        Ok(network)
+    }
+    fn get_hidden_biases_offset(&self) -> usize {
+        NUM_INPUTS * self.hidden_size
+    }
+    fn get_output_weights_offset(&self) -> usize {
+        (NUM_INPUTS + 1) * self.hidden_size
+    }
+    fn get_output_bias_index(&self) -> usize {
+        (NUM_INPUTS + 2) * self.hidden_size
+    }
+    // Returns the hidden layer's values before the ReLU, and the output
+    fn forward(&self, inputs: impl Iterator<Item = usize>) -> (Vec<f64>, f64) {
+        let hidden_biases = self.get_hidden_biases_offset();
+        let mut hidden = self.weights[hidden_biases..hidden_biases + self.hidden_size].to_vec();
+        for input in inputs {
+            let input_weights = &self.weights[input * self.hidden_size..(input + 1) * self.hidden_size];
+            hidden.iter_mut().zip(input_weights).for_each(|(value, weight)| *value += weight);
+        }
+        let output_weights = &self.weights[self.get_output_weights_offset()..self.get_output_bias_index()];
+        let output = hidden
+            .iter()
+            .zip(output_weights)
+            .fold(self.weights[self.get_output_bias_index()], |acc, (value, weight)| acc + value.max(0.0) * weight);
+        (hidden, output)
+    }
+    // Adds the gradient of the log loss for one sample to gradient
+    fn add_gradient(&self, sample: &Sample, gradient: &mut [f64]) {
+        let (hidden, output) = self.forward(sample.inputs.iter().copied());
+        let output_error = sigmoid(output) - sample.result;
+        let output_weights = self.get_output_weights_offset();
+        gradient[self.get_output_bias_index()] += output_error;
+        for (unit, value) in hidden.iter().enumerate() {
+            if *value <= 0.0 {
+                continue;
+            }
+            gradient[output_weights + unit] += output_error * value;
+            let hidden_error = output_error * self.weights[output_weights + unit];
+            gradient[self.get_hidden_biases_offset() + unit] += hidden_error;
+            for input in sample.inputs.iter() {
+                gradient[input * self.hidden_size + unit] += hidden_error;
+            }
+        }
+    }
+}
+
+impl Evaluator for NetworkEvaluator {
+    fn get_terms(&self, board: &Board, colour: Colour) -> Vec<Term> {
+        vec![Term { name: "network", value: self.evaluate(board, colour) }]
+    }
+    fn evaluate(&self, board: &Board, colour: Colour) -> f64 {
+        let (_, output) = self.forward(get_inputs(board));
+        let value = output.clamp(-MAX_VALUE, MAX_VALUE);
+        match colour {
+            Colour::White => value,
+            Colour::Black => -value
+        }
+    }
+}
+
+pub fn train(settings: &TrainSettings) {
+    let positions = dataset::read_dataset(&settings.dataset).unwrap_or_else(|err| dataset::exit_with_error(&err));
+    if positions.is_empty() {
+        dataset::exit_with_error(&format!("{} has no positions in it", settings.dataset));
+    }
+    let mut samples: Vec<Sample> = positions
+        .iter()
+        .map(|position| Sample { inputs: get_inputs(&position.board).collect(), result: position.result })
+        .collect();
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let mut network = NetworkEvaluator::new(settings.hidden_size, &mut rng);
+    // Adam's running averages of each weight's gradient and squared gradient
+    let mut first_moments = vec![0.0; network.weights.len()];
+    let mut second_moments = vec![0.0; network.weights.len()];
+    let mut steps = 0;
+    eprintln!("{} positions, starting loss {:.6}", samples.len(), get_loss(&network, &samples));
+    for epoch in 1..=settings.epochs {
+        samples.shuffle(&mut rng);
+        for batch in samples.chunks(BATCH_SIZE) {
+            let mut gradient = vec![0.0; network.weights.len()];
+            batch.iter().for_each(|sample| network.add_gradient(sample, &mut gradient));
+            steps += 1;
+            let first_correction = 1.0 - ADAM_BETA1.powi(steps);
+            let second_correction = 1.0 - ADAM_BETA2.powi(steps);
+            for (index, grad) in gradient.iter().enumerate() {
+                let grad = grad / batch.len() as f64;
+                first_moments[index] = ADAM_BETA1 * first_moments[index] + (1.0 - ADAM_BETA1) * grad;
+                second_moments[index] = ADAM_BETA2 * second_moments[index] + (1.0 - ADAM_BETA2) * grad * grad;
+                let step = first_moments[index] / first_correction / ((second_moments[index] / second_correction).sqrt() + ADAM_EPSILON);
+                network.weights[index] -= settings.learning_rate * step;
+            }
+        }
+        eprintln!("Epoch {}, loss {:.6}", epoch, get_loss(&network, &samples));
+    }
+    let contents = toml::to_string(&network).unwrap_or_else(|err| dataset::exit_with_error(&format!("Couldn't save the network: {}", err)));
+    match &settings.output {
+        Some(path) => fs::write(path, contents).unwrap_or_else(|err| dataset::exit_with_error(&format!("Couldn't write {}: {}", path, err))),
+        None => print!("{}", contents)
+    }
+}
+
+// The index of every input that is 1 for the board
+fn get_inputs(board: &Board) -> impl Iterator<Item = usize> + '_ {
+    (0..25)
+        .filter_map(|square| match board.board[grid_to_one_d(square / 5, square % 5)] {
+            SquareVal::Piece(piece) => {
+                let piece_index = match (piece.colour, piece.kind) {
+                    (Colour::White, Kind::Pawn) => 0,
+                    (Colour::White, Kind::Knight) => 1,
+                    (Colour::Black, Kind::Pawn) => 2,
+                    (Colour::Black, Kind::Knight) => 3
+                };
+                Some(square * 4 + piece_index)
+            },
+            _ => None
+        })
+}
+
+fn get_loss(network: &NetworkEvaluator, samples: &[Sample]) -> f64 {
+    let total: f64 = samples
+        .iter()
+        .map(|sample| {
+            let (_, output) = network.forward(sample.inputs.iter().copied());
+            // The log loss written in terms of the logit, which stays finite however confident the prediction is
+            output.max(0.0) - output * sample.result + (-output.abs()).exp().ln_1p()
+        })
+        .sum();
+    total / samples.len() as f64
+}
+
+fn sigmoid(value: f64) -> f64 {
+    1.0 / (1.0 + (-value).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn get_samples() -> Vec<Sample> {
+        [("n...p..............PN....", 1.0), (".n... ..p.. .P... ..... ...N.", 0.0), ("..n.p.p...........P.NP...", 0.5)]
+            .iter()
+            .map(|(board, result)| {
+                let board = dataset::parse_board(&board.replace(' ', "")).unwrap();
+                Sample { inputs: get_inputs(&board).collect(), result: *result }
+            })
+            .collect()
+    }
+
+    fn get_network() -> NetworkEvaluator {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut network = NetworkEvaluator::new(3, &mut rng);
+        // Biases start at 0, so give them values to check their gradients properly
+        let biases = network.get_hidden_biases_offset();
+        for index in (biases..biases + 3).chain([network.get_output_bias_index()]) {
+            network.weights[index] = rng.gen_range(-0.5..0.5);
+        }
+        network
+    }
+
+    // Saves the network's fields as given to a file of its own and loads it back
+    fn load_weights(name: &str, hidden_size: usize, weights: Vec<f64>) -> Result<NetworkEvaluator, String> {
+        let path = env::temp_dir().join(format!("babylon-network-test-{}-{}.toml", process::id(), name));
+        let path = path.to_str().unwrap();
+        fs::write(path, toml::to_string(&NetworkEvaluator { hidden_size, weights }).unwrap()).unwrap();
+        let network = NetworkEvaluator::load(path);
+        fs::remove_file(path).unwrap();
+        network
+    }
+
+    #[test]
+    fn inputs_are_the_pieces_on_their_squares() {
+        let board = dataset::parse_board(&"P...n ..... ..... ..... ....p".replace(' ', "")).unwrap();
+        assert_eq!(get_inputs(&board).collect::<Vec<usize>>(), vec![0, 4 * 4 + 3, 24 * 4 + 2]);
+        let board = dataset::parse_board(&"..... ..... ..N.. ..... .....".replace(' ', "")).unwrap();
+        assert_eq!(get_inputs(&board).collect::<Vec<usize>>(), vec![12 * 4 + 1]);
+    }
+
+    #[test]
+    fn output_matches_multiplying_through_every_input() {
+        let network = get_network();
+        for sample in get_samples() {
+            let mut output = network.weights[network.get_output_bias_index()];
+            for unit in 0..network.hidden_size {
+                let hidden: f64 = (0..NUM_INPUTS)
+                    .map(|input| if sample.inputs.contains(&input) { network.weights[input * network.hidden_size + unit] } else { 0.0 })
+                    .sum::<f64>()
+                    + network.weights[network.get_hidden_biases_offset() + unit];
+                output += hidden.max(0.0) * network.weights[network.get_output_weights_offset() + unit];
+            }
+            let (_, forward_output) = network.forward(sample.inputs.iter().copied());
+            assert!((forward_output - output).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn gradient_is_the_prediction_error_times_the_slope_of_the_output() {
+        let network = get_network();
+        let step = 1e-6;
+        for sample in get_samples() {
+            let mut gradient = vec![0.0; network.weights.len()];
+            network.add_gradient(&sample, &mut gradient);
+            let (_, output) = network.forward(sample.inputs.iter().copied());
+            let output_error = sigmoid(output) - sample.result;
+            for (index, grad) in gradient.iter().enumerate() {
+                let mut changed = NetworkEvaluator { hidden_size: network.hidden_size, weights: network.weights.clone() };
+                changed.weights[index] += step;
+                let (_, upper_output) = changed.forward(sample.inputs.iter().copied());
+                changed.weights[index] -= 2.0 * step;
+                let (_, lower_output) = changed.forward(sample.inputs.iter().copied());
+                let expected = output_error * (upper_output - lower_output) / (2.0 * step);
+                assert!((grad - expected).abs() < 1e-6, "weight {} has gradient {} but should have {}", index, grad, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn hidden_units_that_are_off_pass_back_no_gradient() {
+        let mut network = get_network();
+        let sample = &get_samples()[0];
+        // Turn the first hidden unit off for every input
+        let bias = network.get_hidden_biases_offset();
+        network.weights[bias] = -100.0;
+        let mut gradient = vec![0.0; network.weights.len()];
+        network.add_gradient(sample, &mut gradient);
+        assert_eq!(gradient[bias], 0.0);
+        assert_eq!(gradient[network.get_output_weights_offset()], 0.0);
+        assert!(sample.inputs.iter().all(|input| gradient[input * network.hidden_size] == 0.0));
+        // Inputs for pieces that aren't on the board never get a gradient
+        let unused = (0..NUM_INPUTS).find(|input| !sample.inputs.contains(input)).unwrap();
+        assert!((0..network.hidden_size).all(|unit| gradient[unused * network.hidden_size + unit] == 0.0));
+    }
+
+    #[test]
+    fn evaluation_flips_sign_with_colour() {
+        let network = get_network();
+        for board in ["n...p..............PN....", "..n.p.p...........P.NP..."] {
+            let board = dataset::parse_board(board).unwrap();
+            let value = network.evaluate(&board, Colour::White);
+            assert_ne!(value, 0.0);
+            assert_eq!(network.evaluate(&board, Colour::Black), -value);
+        }
+    }
+
+    #[test]
+    fn load_rejects_the_wrong_number_of_weights() {
+        let network = get_network();
+        assert!(load_weights("right", 3, network.weights.clone()).is_ok());
+        assert!(load_weights("short", 3, network.weights[1..].to_vec()).is_err());
+        assert!(load_weights("hidden", 4, network.weights.clone()).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+/*
+    Command line options for the CLI game and the tuner.
+
+    Usage: babylon [--engine matrix|mcts|regret] [--depth N] [--time-ms N] [--nodes N] [--table-size N]
+                   [--threads N] [--iterations N] [--selection ucb|exp3] [--exploration C] [--show-strategy]
+                   [--show-evaluation] [--seed N] [--tables FILE | --network FILE] [--penalty-points]
+                   [--no-progress-limit N] [--move-time SECONDS] [--pass never|blocked|always]
+           babylon tune DATASET [--tables FILE] [--output FILE] [--epochs N] [--learning-rate R]
+           babylon selfplay [--games N] [--seed N] [--jobs N] [--max-move-pairs N] [--no-progress-limit N]
+                            [--pass never|blocked|always] [--white OPTIONS] [--black OPTIONS]
+                            [--tables FILE | --network FILE] [--output FILE]
+           babylon train DATASET [--hidden N] [--epochs N] [--learning-rate R] [--seed N] [--output FILE]
+
+    The matrix engine searches to a fixed --depth unless given a --time-ms or --nodes budget, in which case it
+    deepens iteratively until the budget runs out (or --depth is reached, if that was given too). --table-size sets
+    the number of transposition table entries it keeps, 0 turns the table off. --threads defaults to the number of
+    cores available, or to 1 for selfplay's engines as the games already run in parallel.
+
+    The regret engine runs for --time-ms, or for a fixed number of --iterations if that's given instead. With a --seed
+    the computer plays the same game every time against the same moves, as long as no engine is limited by time.
+
+    --tables loads the evaluation's piece-square tables from a TOML file instead of using the built-in ones.
+    --network evaluates with a network saved by train instead.
+
+    With --penalty-points an illegal move isn't asked for again. It's given a penalty point and not played, and a side
+    with two penalty points loses.
+
+    A game is drawn when the same position comes up three times, or after --no-progress-limit move pairs (50 by
+    default) without a capture or a pawn moving.
+
+    --pass says when a side may pass, standing still while the other side moves. With never (the default) a side
+    with no legal moves draws the game, with blocked it passes instead, and with always either side may pass on any
+    move. It's a draw whatever the rule once neither side has a piece that can move.
+
+    Entering resign instead of a move resigns the game. With --move-time, not entering a move within that many
+    seconds loses the game on time.
+
+    tune fits the piece-square tables to the positions in DATASET, starting from --tables, and writes them to
+    --output (or prints them if not given).
+
+    selfplay plays the computer against itself and writes every position, strategy and move played to --output (or
+    prints them if not given), in a form the tuner can read. --white and --black take each side's engine options as
+    one argument, e.g. --white "--engine mcts --iterations 5000". --jobs is how many games to play at once and
+    defaults to the number of cores available. Without a --seed a random one is picked and printed.
+
+    train fits a network evaluator with a --hidden layer of the given size to the positions in DATASET and writes it
+    to --output (or prints it if not given).
+*/
+
+use std::{env, thread, time::Duration};
+use crate::{
+    evaluation::{EvaluatorChoice, PieceSquareEvaluator},
+    mcts::{MctsSettings, Selection},
+    network::{NetworkEvaluator, TrainSettings},
+    regret::RegretSettings,
+    search::SearchLimits,
+    selfplay::SelfPlaySettings,
+    tuner::TuneSettings,
+    Engine, PassRule
+};
+
+const USAGE: &str = "Usage: babylon [--engine matrix|mcts|regret] [--depth N] [--time-ms N] [--nodes N] [--table-size N] \
+                     [--threads N] [--iterations N] [--selection ucb|exp3] [--exploration C] [--show-strategy] \
+                     [--show-evaluation] [--seed N] [--tables FILE | --network FILE] [--penalty-points] \
+                     [--no-progress-limit N] [--move-time SECONDS] [--pass never|blocked|always]\n       \
+                     babylon tune DATASET [--tables FILE] [--output FILE] [--epochs N] [--learning-rate R]\n       \
+                     babylon selfplay [--games N] [--seed N] [--jobs N] [--max-move-pairs N] [--no-progress-limit N] \
+                     [--pass never|blocked|always] [--white OPTIONS] [--black OPTIONS] [--tables FILE | --network FILE] [--output FILE]\n       \
+                     babylon train DATASET [--hidden N] [--epochs N] [--learning-rate R] [--seed N] [--output FILE]";
+
+const DEFAULT_DEPTH: u8 = 2;
+const MAX_ITERATIVE_DEPTH: u8 = 32;
+const DEFAULT_ITERATIONS: u32 = 20000;
+const DEFAULT_UCB_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+const DEFAULT_EXP3_EXPLORATION: f64 = 0.2;
+const DEFAULT_REGRET_EXPLORATION: f64 = 0.1;
+const DEFAULT_TIME_MS: u64 = 1000;
+const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+const DEFAULT_EPOCHS: u32 = 1000;
+const DEFAULT_LEARNING_RATE: f64 = 1.0;
+const DEFAULT_GAMES: u64 = 100;
+const DEFAULT_MAX_MOVE_PAIRS: u32 = 200;
+const DEFAULT_NO_PROGRESS_LIMIT: u32 = 50;
+const DEFAULT_HIDDEN_SIZE: usize = 32;
+const DEFAULT_TRAIN_EPOCHS: u32 = 20;
+const DEFAULT_TRAIN_LEARNING_RATE: f64 = 0.001;
+
+pub enum Command {
+    Play(Options),
+    Tune(Box<TuneSettings>),
+    SelfPlay(SelfPlaySettings),
+    Train(TrainSettings)
+}
+
+pub struct Options {
+    pub engine: Engine,
+    pub show_strategy: bool,
+    pub show_evaluation: bool,
+    pub seed: Option<u64>,
+    pub evaluator: EvaluatorChoice,
+    pub penalty_points: bool,
+    pub no_progress_limit: u32,
+    pub move_time_limit: Option<Duration>,
+    pub pass_rule: PassRule
+}
+
+enum EngineKind {
+    Matrix,
+    Mcts,
+    Regret
+}
+
+// The engine options as given, turned into an Engine once they've all been read
+struct EngineArgs {
+    engine_kind: EngineKind,
+    time_ms: Option<u64>,
+    node_budget: Option<u64>,
+    depth: Option<u8>,
+    table_size: usize,
+    threads: Option<usize>,
+    iterations: Option<u32>,
+    selection: Selection,
+    exploration: Option<f64>
+}
+
+pub fn parse_command() -> Command {
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("tune") => {
+            args.next();
+            Command::Tune(Box::new(parse_tune_settings(args)))
+        },
+        Some("selfplay") => {
+            args.next();
+            Command::SelfPlay(parse_self_play_settings(args))
+        },
+        Some("train") => {
+            args.next();
+            Command::Train(parse_train_settings(args))
+        },
+        _ => Command::Play(parse_options(args))
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Options {
+    let mut engine_args = EngineArgs::new();
+    let mut show_strategy = false;
+    let mut show_evaluation = false;
+    let mut seed = None;
+    let mut evaluator = EvaluatorChoice::PieceSquare(Box::default());
+    let mut penalty_points = false;
+    let mut no_progress_limit = DEFAULT_NO_PROGRESS_LIMIT;
+    let mut move_time_limit = None;
+    let mut pass_rule = PassRule::Never;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--move-time" => move_time_limit = Some(Duration::from_secs(parse_value(&arg, args.next(), |seconds| *seconds > 0))),
+            "--no-progress-limit" => no_progress_limit = parse_value(&arg, args.next(), |limit| *limit > 0),
+            "--pass" => pass_rule = parse_pass_rule(&arg, args.next()),
+            "--show-strategy" => show_strategy = true,
+            "--penalty-points" => penalty_points = true,
+            "--show-evaluation" => show_evaluation = true,
+            "--seed" => seed = Some(parse_value(&arg, args.next(), |_| true)),
+            "--tables" => evaluator = EvaluatorChoice::PieceSquare(Box::new(parse_tables(&arg, args.next()))),
+            "--network" => evaluator = EvaluatorChoice::Network(parse_network(&arg, args.next())),
+            _ => engine_args.parse_arg(&arg, &mut args)
+        }
+    }
+    Options {
+        engine: engine_args.get_engine(get_core_count()),
+        show_strategy,
+        show_evaluation,
+        seed,
+        evaluator,
+        penalty_points,
+        no_progress_limit,
+        move_time_limit,
+        pass_rule
+    }
+}
+
+impl EngineArgs {
+    fn new() -> EngineArgs {
+        EngineArgs {
+            engine_kind: EngineKind::Matrix,
+            time_ms: None,
+            node_budget: None,
+            depth: None,
+            table_size: DEFAULT_TABLE_SIZE,
+            threads: None,
+            iterations: None,
+            selection: Selection::Ucb,
+            exploration: None
+        }
+    }
+    fn parse_arg(&mut self, arg: &str, args: &mut impl Iterator<Item = String>) {
+        match arg {
+            "--engine" => self.engine_kind = match args.next().as_deref() {
+                Some("matrix") => EngineKind::Matrix,
+                Some("mcts") => EngineKind::Mcts,
+                Some("regret") => EngineKind::Regret,
+                _ => exit_with_usage(&format!("Invalid value for {}", arg))
+            },
+            "--depth" => self.depth = Some(parse_value(arg, args.next(), |depth| *depth > 0)),
+            "--iterations" => self.iterations = Some(parse_value(arg, args.next(), |iterations| *iterations > 0)),
+            "--selection" => self.selection = match args.next().as_deref() {
+                Some("ucb") => Selection::Ucb,
+                Some("exp3") => Selection::Exp3,
+                _ => exit_with_usage(&format!("Invalid value for {}", arg))
+            },
+            "--exploration" => self.exploration = Some(parse_value(arg, args.next(), |exploration: &f64| *exploration >= 0.0)),
+            "--time-ms" => self.time_ms = Some(parse_value(arg, args.next(), |time_ms| *time_ms > 0)),
+            "--nodes" => self.node_budget = Some(parse_value(arg, args.next(), |nodes| *nodes > 0)),
+            "--table-size" => self.table_size = parse_value(arg, args.next(), |_| true),
+            "--threads" => self.threads = Some(parse_value(arg, args.next(), |threads| *threads > 0)),
+            _ => exit_with_usage(&format!("Unknown option {}", arg))
+        }
+    }
+    fn get_engine(&self, default_threads: usize) -> Engine {
+        match self.engine_kind {
+            EngineKind::Matrix => {
+                let has_budget = self.time_ms.is_some() || self.node_budget.is_some();
+                Engine::Matrix(SearchLimits {
+                    max_depth: self.depth.unwrap_or(if has_budget { MAX_ITERATIVE_DEPTH } else { DEFAULT_DEPTH }),
+                    time_budget: self.time_ms.map(Duration::from_millis),
+                    node_budget: self.node_budget,
+                    table_size: self.table_size,
+                    threads: self.threads.unwrap_or(default_threads)
+                })
+            },
+            EngineKind::Mcts => {
+                let exploration = match (self.selection, self.exploration) {
+                    (Selection::Exp3, Some(gamma)) if gamma > 1.0 => exit_with_usage("--exploration must be at most 1 with exp3"),
+                    (_, Some(exploration)) => exploration,
+                    (Selection::Ucb, None) => DEFAULT_UCB_EXPLORATION,
+                    (Selection::Exp3, None) => DEFAULT_EXP3_EXPLORATION
+                };
+                Engine::Mcts(MctsSettings { iterations: self.iterations.unwrap_or(DEFAULT_ITERATIONS), exploration, selection: self.selection })
+            },
+            EngineKind::Regret => {
+                let exploration = match self.exploration {
+                    Some(gamma) if gamma > 1.0 => exit_with_usage("--exploration must be at most 1 with regret"),
+                    Some(gamma) => gamma,
+                    None => DEFAULT_REGRET_EXPLORATION
+                };
+                let time_budget = Duration::from_millis(self.time_ms.unwrap_or(DEFAULT_TIME_MS));
+                Engine::Regret(RegretSettings { time_budget, iterations: self.iterations, exploration })
+            }
+        }
+    }
+}
+
+fn parse_tune_settings(mut args: impl Iterator<Item = String>) -> TuneSettings {
+    let mut dataset = None;
+    let mut initial_tables = PieceSquareEvaluator::default();
+    let mut output = None;
+    let mut epochs = DEFAULT_EPOCHS;
+    let mut learning_rate = DEFAULT_LEARNING_RATE;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tables" => initial_tables = parse_tables(&arg, args.next()),
+            "--output" => output = Some(parse_value(&arg, args.next(), |_: &String| true)),
+            "--epochs" => epochs = parse_value(&arg, args.next(), |epochs| *epochs > 0),
+            "--learning-rate" => learning_rate = parse_value(&arg, args.next(), |rate: &f64| *rate > 0.0),
+            _ if dataset.is_none() && !arg.starts_with("--") => dataset = Some(arg),
+            _ => exit_with_usage(&format!("Unknown option {}", arg))
+        }
+    }
+    let Some(dataset) = dataset else {
+        exit_with_usage("tune needs a dataset")
+    };
+    TuneSettings { dataset, initial_tables, output, epochs, learning_rate }
+}
+
+fn parse_self_play_settings(mut args: impl Iterator<Item = String>) -> SelfPlaySettings {
+    let mut games = DEFAULT_GAMES;
+    let mut seed = None;
+    let mut jobs = None;
+    let mut max_move_pairs = DEFAULT_MAX_MOVE_PAIRS;
+    let mut no_progress_limit = DEFAULT_NO_PROGRESS_LIMIT;
+    let mut pass_rule = PassRule::Never;
+    // Each job's engines get one thread unless told otherwise, so the games don't compete for the cores
+    let mut white_engine = EngineArgs::new().get_engine(1);
+    let mut black_engine = EngineArgs::new().get_engine(1);
+    let mut evaluator = EvaluatorChoice::PieceSquare(Box::default());
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => games = parse_value(&arg, args.next(), |games| *games > 0),
+            "--seed" => seed = Some(parse_value(&arg, args.next(), |_| true)),
+            "--jobs" => jobs = Some(parse_value(&arg, args.next(), |jobs| *jobs > 0)),
+            "--max-move-pairs" => max_move_pairs = parse_value(&arg, args.next(), |max_move_pairs| *max_move_pairs > 0),
+            "--no-progress-limit" => no_progress_limit = parse_value(&arg, args.next(), |limit| *limit > 0),
+            "--pass" => pass_rule = parse_pass_rule(&arg, args.next()),
+            "--white" => white_engine = parse_engine(&arg, args.next()),
+            "--black" => black_engine = parse_engine(&arg, args.next()),
+            "--tables" => evaluator = EvaluatorChoice::PieceSquare(Box::new(parse_tables(&arg, args.next()))),
+            "--network" => evaluator = EvaluatorChoice::Network(parse_network(&arg, args.next())),
+            "--output" => output = Some(parse_value(&arg, args.next(), |_: &String| true)),
+            _ => exit_with_usage(&format!("Unknown option {}", arg))
+        }
+    }
+    let seed = seed.unwrap_or_else(|| {
+        let seed = rand::random();
+        eprintln!("Using seed {}", seed);
+        seed
+    });
+    SelfPlaySettings {
+        games,
+        seed,
+        jobs: jobs.unwrap_or_else(get_core_count),
+        max_move_pairs,
+        no_progress_limit,
+        pass_rule,
+        white_engine,
+        black_engine,
+        evaluator,
+        output
+    }
+}
+
+// Engine options given as a single argument, split up and read as if they'd been given to the game
+fn parse_engine(arg: &str, options: Option<String>) -> Engine {
+    let Some(options) = options else {
+        exit_with_usage(&format!("Invalid value for {}", arg))
+    };
+    let mut engine_args = EngineArgs::new();
+    let mut args = options.split_whitespace().map(String::from);
+    while let Some(arg) = args.next() {
+        engine_args.parse_arg(&arg, &mut args);
+    }
+    engine_args.get_engine(1)
+}
+
+fn get_core_count() -> usize {
+    thread::available_parallelism().map_or(1, |cores| cores.get())
+}
+
+fn parse_train_settings(mut args: impl Iterator<Item = String>) -> TrainSettings {
+    let mut dataset = None;
+    let mut hidden_size = DEFAULT_HIDDEN_SIZE;
+    let mut epochs = DEFAULT_TRAIN_EPOCHS;
+    let mut learning_rate = DEFAULT_TRAIN_LEARNING_RATE;
+    let mut seed = 0;
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hidden" => hidden_size = parse_value(&arg, args.next(), |hidden_size| *hidden_size > 0),
+            "--epochs" => epochs = parse_value(&arg, args.next(), |epochs| *epochs > 0),
+            "--learning-rate" => learning_rate = parse_value(&arg, args.next(), |rate: &f64| *rate > 0.0),
+            "--seed" => seed = parse_value(&arg, args.next(), |_| true),
+            "--output" => output = Some(parse_value(&arg, args.next(), |_: &String| true)),
+            _ if dataset.is_none() && !arg.starts_with("--") => dataset = Some(arg),
+            _ => exit_with_usage(&format!("Unknown option {}", arg))
+        }
+    }
+    let Some(dataset) = dataset else {
+        exit_with_usage("train needs a dataset")
+    };
+    TrainSettings { dataset, hidden_size, epochs, learning_rate, seed, output }
+}
+
+fn parse_pass_rule(arg: &str, rule: Option<String>) -> PassRule {
+    match rule.as_deref() {
+        Some("never") => PassRule::Never,
+        Some("blocked") => PassRule::WhenBlocked,
+        Some("always") => PassRule::Always,
+        _ => exit_with_usage(&format!("Invalid value for {}", arg))
+    }
+}
+
+fn parse_network(arg: &str, path: Option<String>) -> NetworkEvaluator {
+    match path {
+        Some(path) => NetworkEvaluator::load(&path).unwrap_or_else(|err| exit_with_usage(&err)),
+        None => exit_with_usage(&format!("Invalid value for {}", arg))
+    }
+}
+
+fn parse_tables(arg: &str, path: Option<String>) -> PieceSquareEvaluator {
+    match path {
+        Some(path) => PieceSquareEvaluator::load(&path).unwrap_or_else(|err| exit_with_usage(&err)),
+        None => exit_with_usage(&format!("Invalid value for {}", arg))
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(arg: &str, value: Option<String>, is_valid: impl Fn(&T) -> bool) -> T {
+    match value.map(|val| val.parse::<T>()) {
+        Some(Ok(val)) if is_valid(&val) => val,
+        _ => exit_with_usage(&format!("Invalid value for {}", arg))
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_threads(engine: Engine) -> usize {
+        let Engine::Matrix(limits) = engine else {
+            panic!("expected the matrix engine");
+        };
+        limits.threads
+    }
+
+    #[test]
+    fn self_play_engines_get_one_thread_unless_told_otherwise() {
+        assert_eq!(get_threads(parse_engine("--white", Some("--depth 2".to_string()))), 1);
+        assert_eq!(get_threads(parse_engine("--white", Some("--depth 2 --threads 3".to_string()))), 3);
+        let settings = parse_self_play_settings(["--black", "--threads 2"].map(String::from).into_iter());
+        assert_eq!(get_threads(settings.white_engine), 1);
+        assert_eq!(get_threads(settings.black_engine), 2);
+    }
+}
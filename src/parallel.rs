@@ -0,0 +1,150 @@
+/*
+    Running independent pieces of work on several threads at once.
+
+    map_in_parallel starts threads for a single list of items, which suits a few long pieces of work like whole
+    games. For lots of short pieces of work handed out as they come up, like during a search, a JobQueue is worked
+    through by a pool of threads started once with with_workers. A thread waiting on a batch of jobs runs queued jobs
+    itself in the meantime, so jobs can queue batches of their own without every thread ending up stuck waiting.
+*/
+
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::{AtomicUsize, Ordering}, Arc, Condvar, Mutex},
+    thread
+};
+
+pub struct JobQueue<J, R> {
+    state: Mutex<QueueState<J, R>>,
+    // Signalled whenever a job is queued or finished, or the queue is closed
+    changed: Condvar
+}
+
+struct QueueState<J, R> {
+    jobs: VecDeque<QueuedJob<J, R>>,
+    closed: bool
+}
+
+struct QueuedJob<J, R> {
+    job: J,
+    index: usize,
+    batch: Arc<Batch<R>>
+}
+
+struct Batch<R> {
+    // A job that panicked has its panic passed on to whoever queued the batch
+    results: Mutex<Vec<Option<thread::Result<R>>>>,
+    // Only changed with the queue locked, so a thread can't miss the batch finishing between checking and waiting
+    remaining: AtomicUsize
+}
+
+// Closes the queue when dropped, so the workers stop even if the body they're running alongside panics
+struct QueueCloser<'a, J, R>(&'a JobQueue<J, R>);
+
+// Maps every item to its result using up to the given number of threads, each taking the next unclaimed item until
+// there are none left. The results are in the same order as the items whichever thread worked them out.
+pub fn map_in_parallel<T: Sync, U: Send>(threads: usize, items: &[T], map: impl Fn(&T) -> U + Sync) -> Vec<U> {
+    if threads <= 1 || items.len() <= 1 {
+        return items.iter().map(map).collect();
+    }
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    thread::scope(|scope| {
+        for _ in 0..threads.min(items.len()) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = map(item);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// Runs body with threads - 1 workers running jobs from the queue alongside it, so that up to threads jobs run at once
+// counting the ones the body's thread runs while it waits
+pub fn with_workers<J: Send, R: Send, T>(threads: usize, queue: &JobQueue<J, R>, run: impl Fn(J) -> R + Sync, body: impl FnOnce() -> T) -> T {
+    thread::scope(|scope| {
+        for _ in 1..threads {
+            scope.spawn(|| queue.work(&run));
+        }
+        let _closer = QueueCloser(queue);
+        body()
+    })
+}
+
+impl<J: Send, R: Send> JobQueue<J, R> {
+    pub fn new() -> JobQueue<J, R> {
+        JobQueue {
+            state: Mutex::new(QueueState { jobs: VecDeque::new(), closed: false }),
+            changed: Condvar::new()
+        }
+    }
+    // Runs every job and returns the results in the same order, whichever threads worked them out. run should do the
+    // same as the workers' run, as either might end up running any job.
+    pub fn run_all(&self, jobs: Vec<J>, run: &impl Fn(J) -> R) -> Vec<R> {
+        let batch = Arc::new(Batch {
+            results: Mutex::new((0..jobs.len()).map(|_| None).collect()),
+            remaining: AtomicUsize::new(jobs.len())
+        });
+        let mut state = self.state.lock().unwrap();
+        state.jobs.extend(jobs.into_iter().enumerate().map(|(index, job)| QueuedJob { job, index, batch: batch.clone() }));
+        self.changed.notify_all();
+        while batch.remaining.load(Ordering::Relaxed) > 0 {
+            // Our own jobs first, as they're what we're waiting for
+            let position = state.jobs.iter().position(|queued| Arc::ptr_eq(&queued.batch, &batch)).unwrap_or(0);
+            match state.jobs.remove(position) {
+                Some(queued) => {
+                    drop(state);
+                    self.run_job(queued, run);
+                    state = self.state.lock().unwrap();
+                }
+                None => state = self.changed.wait(state).unwrap()
+            }
+        }
+        drop(state);
+        let results = std::mem::take(&mut *batch.results.lock().unwrap());
+        results
+            .into_iter()
+            .map(|result| match result.unwrap() {
+                Ok(result) => result,
+                Err(payload) => panic::resume_unwind(payload)
+            })
+            .collect()
+    }
+    // Runs queued jobs until the queue is closed
+    fn work(&self, run: &impl Fn(J) -> R) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(queued) = state.jobs.pop_front() {
+                drop(state);
+                self.run_job(queued, run);
+                state = self.state.lock().unwrap();
+            } else if state.closed {
+                return;
+            } else {
+                state = self.changed.wait(state).unwrap();
+            }
+        }
+    }
+    fn run_job(&self, queued: QueuedJob<J, R>, run: &impl Fn(J) -> R) {
+        let QueuedJob { job, index, batch } = queued;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run(job)));
+        batch.results.lock().unwrap()[index] = Some(result);
+        let _state = self.state.lock().unwrap();
+        batch.remaining.fetch_sub(1, Ordering::Relaxed);
+        self.changed.notify_all();
+    }
+}
+
+impl<J, R> Drop for QueueCloser<'_, J, R> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().closed = true;
+        self.0.changed.notify_all();
+    }
+}
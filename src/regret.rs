@@ -0,0 +1,199 @@
+/*
+    Online regret minimisation over the game tree below the current board.
+
+    This is Monte Carlo tree search where each player picks their move at every node by regret matching: moves are
+    played in proportion to how much better they would have done than what was actually played, so far. Only the
+    sampled path is updated each iteration (outcome sampling), with rewards importance weighted by the probability
+    the move had of being picked. The average of the strategies played at a node converges to an equilibrium,
+    so the root's average strategy is what we return.
+
+    Rewards use the same scale as the tree search: 1 for a win, 0.5 for a draw and 0 for a loss.
+*/
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+use rand::{seq::SliceRandom, Rng};
+use crate::{mcts, search::SearchResult, Board, Colour, Move};
+
+#[derive(Clone, Copy)]
+pub struct RegretSettings {
+    pub time_budget: Duration,
+    // Run exactly this many iterations instead, so the result only depends on the RNG and not how fast we are
+    pub iterations: Option<u32>,
+    // Share of moves picked uniformly at random, so every move keeps getting sampled
+    pub exploration: f64
+}
+
+struct Node {
+    board: Board,
+    our_moves: Vec<Move>,
+    opponent_moves: Vec<Move>,
+    our_regrets: RegretTable,
+    opponent_regrets: RegretTable,
+    children: HashMap<(usize, usize), Node>,
+    terminal_reward: Option<f64>
+}
+
+struct RegretTable {
+    regrets: Vec<f64>,
+    strategy_sums: Vec<f64>
+}
+
+pub fn search<R: Rng>(board: &Board, colour: Colour, settings: &RegretSettings, rng: &mut R) -> Option<SearchResult> {
+    let mut root = Node::new(*board, colour);
+    if root.terminal_reward.is_some() {
+        return None;
+    }
+    let deadline = Instant::now() + settings.time_budget;
+    // Always run at least one iteration so there's an average strategy to return
+    let mut iterations = 0;
+    loop {
+        root.run_iteration(colour, settings.exploration, rng);
+        iterations += 1;
+        let finished = match settings.iterations {
+            Some(max_iterations) => iterations >= max_iterations,
+            None => Instant::now() >= deadline
+        };
+        if finished {
+            break;
+        }
+    }
+    Some(SearchResult {
+        strategy: root.our_regrets.get_average_strategy(),
+        moves: root.our_moves
+    })
+}
+
+impl Node {
+    fn new(board: Board, colour: Colour) -> Node {
+        let our_moves = board.get_all_legal_moves(colour);
+        let opponent_moves = board.get_all_legal_moves(-colour);
+        Node {
+            terminal_reward: mcts::get_terminal_reward(&board, colour, &our_moves, &opponent_moves),
+            our_regrets: RegretTable::new(our_moves.len()),
+            opponent_regrets: RegretTable::new(opponent_moves.len()),
+            children: HashMap::new(),
+            board,
+            our_moves,
+            opponent_moves
+        }
+    }
+    fn run_iteration<R: Rng>(&mut self, colour: Colour, exploration: f64, rng: &mut R) -> f64 {
+        if let Some(reward) = self.terminal_reward {
+            return reward;
+        }
+        let our_strategy = self.our_regrets.get_sampling_strategy(exploration);
+        let opponent_strategy = self.opponent_regrets.get_sampling_strategy(exploration);
+        let our_index = sample_index(&our_strategy, rng);
+        let opponent_index = sample_index(&opponent_strategy, rng);
+        let reward = match self.children.get_mut(&(our_index, opponent_index)) {
+            Some(child) => child.run_iteration(colour, exploration, rng),
+            None => {
+                let child_board = self.board.get_boards_possible_for_move(
+                    &self.our_moves[our_index],
+                    &colour,
+                    &[self.opponent_moves[opponent_index]]
+                )[0];
+                let child = Node::new(child_board, colour);
+                let reward = child.terminal_reward.unwrap_or_else(|| mcts::playout(&child_board, colour, rng));
+                self.children.insert((our_index, opponent_index), child);
+                reward
+            }
+        };
+        self.our_regrets.update(our_index, &our_strategy, reward);
+        self.opponent_regrets.update(opponent_index, &opponent_strategy, 1.0 - reward);
+        reward
+    }
+}
+
+impl RegretTable {
+    fn new(num_moves: usize) -> RegretTable {
+        RegretTable {
+            regrets: vec![0.0; num_moves],
+            strategy_sums: vec![0.0; num_moves]
+        }
+    }
+    // Regret matching: play each move in proportion to its positive regret, or uniformly if none are positive
+    fn get_current_strategy(&self) -> Vec<f64> {
+        let positive_regret_sum: f64 = self.regrets.iter().map(|regret| regret.max(0.0)).sum();
+        if positive_regret_sum > 0.0 {
+            self.regrets.iter().map(|regret| regret.max(0.0) / positive_regret_sum).collect()
+        } else {
+            vec![1.0 / self.regrets.len() as f64; self.regrets.len()]
+        }
+    }
+    fn get_sampling_strategy(&self, exploration: f64) -> Vec<f64> {
+        let uniform = 1.0 / self.regrets.len() as f64;
+        self.get_current_strategy()
+            .iter()
+            .map(|prob| (1.0 - exploration) * prob + exploration * uniform)
+            .collect()
+    }
+    fn update(&mut self, played: usize, sampling_strategy: &[f64], reward: f64) {
+        // Every move's estimated reward is 0 apart from the one played, whose reward is scaled up by how unlikely
+        // it was to be played. Regret is how much better each estimate is than the current strategy's, which is
+        // the played move's estimate weighted by how often the current strategy plays it.
+        let current_strategy = self.get_current_strategy();
+        let played_estimate = reward / sampling_strategy[played];
+        let strategy_estimate = current_strategy[played] * played_estimate;
+        for (index, regret) in self.regrets.iter_mut().enumerate() {
+            let estimate = if index == played { played_estimate } else { 0.0 };
+            *regret += estimate - strategy_estimate;
+        }
+        self.strategy_sums
+            .iter_mut()
+            .zip(current_strategy)
+            .for_each(|(sum, prob)| *sum += prob);
+    }
+    fn get_average_strategy(&self) -> Vec<f64> {
+        let total: f64 = self.strategy_sums.iter().sum();
+        self.strategy_sums.iter().map(|sum| sum / total).collect()
+    }
+}
+
+fn sample_index<R: Rng>(strategy: &[f64], rng: &mut R) -> usize {
+    let indices: Vec<usize> = (0..strategy.len()).collect();
+    *indices
+        .choose_weighted(rng, |index| strategy[*index])
+        .unwrap_or(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use crate::dataset;
+
+    // Our reward for each pair of moves, the opponent getting 1 minus it. Either side playing its first move a third
+    // of the time leaves the other with nothing to gain by changing, so that's the equilibrium.
+    const REWARDS: [[f64; 2]; 2] = [[1.0, 0.0], [0.0, 0.5]];
+
+    #[test]
+    fn average_strategies_find_the_equilibrium_of_a_small_game() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut our_regrets = RegretTable::new(2);
+        let mut opponent_regrets = RegretTable::new(2);
+        for _ in 0..100000 {
+            let our_strategy = our_regrets.get_sampling_strategy(0.1);
+            let opponent_strategy = opponent_regrets.get_sampling_strategy(0.1);
+            let our_index = sample_index(&our_strategy, &mut rng);
+            let opponent_index = sample_index(&opponent_strategy, &mut rng);
+            let reward = REWARDS[our_index][opponent_index];
+            our_regrets.update(our_index, &our_strategy, reward);
+            opponent_regrets.update(opponent_index, &opponent_strategy, 1.0 - reward);
+        }
+        for regrets in [our_regrets, opponent_regrets] {
+            let strategy = regrets.get_average_strategy();
+            assert!((strategy[0] - 1.0 / 3.0).abs() < 0.03, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn forced_win_gets_most_of_the_strategy() {
+        // Black's last pawn on c3 is stuck behind White's pawn, so b1-c3 takes it whatever Black does
+        let board = dataset::parse_board(&"n.... ..... ..p.. ..P.. .N...".replace(' ', "")).unwrap();
+        let settings = RegretSettings { time_budget: Duration::ZERO, iterations: Some(2000), exploration: 0.1 };
+        let search_result = search(&board, Colour::White, &settings, &mut StdRng::seed_from_u64(0)).unwrap();
+        let index = search_result.moves.iter().position(|mov| mov.to_string() == "b1-c3").unwrap();
+        assert!(search_result.strategy[index] > 0.5, "{:?}", search_result.strategy);
+    }
+}
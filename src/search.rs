@@ -0,0 +1,746 @@
+/*
+    Depth limited search over the simultaneous move game tree.
+
+    Every node is a matrix game: our moves are the rows, our opponents moves are the columns and each cell is the
+    board reached by that move pair. A cell's payoff is the value of the subgame below it, found by solving that
+    subgame's matrix in turn (backward induction), until the depth runs out or the game is over.
+
+    Subgames are pruned with simultaneous move alpha-beta (Saffidine, Finnsson & Buro 2012). Each cell keeps a
+    pessimistic and optimistic bound on its value. Before a cell is searched we work out how low it would have to be
+    for its row to be dominated by a mix of the other rows, and how high for its column to be dominated by a mix of
+    the other columns. The subgame is searched with those as its window, and if it falls outside the window the
+    row or column is removed without searching the rest of it. The node's window from its parent is treated as an
+    extra row worth alpha everywhere and an extra column worth beta everywhere.
+
+    Cells start with bounds from the two serialised versions of their subgame, where one player has to commit to a
+    move before the other replies. Moving first can only hurt, so these are solved with ordinary alpha-beta to give
+    a pessimistic and optimistic bound on the simultaneous value.
+
+    Subgame results go in a transposition table, which tightens the starting bounds of cells that have been seen
+    before and lets rows be searched in order of how likely a shallower search thought they were to be played.
+
+    A pool of threads lasts the whole search. The root's cells are searched a row at a time on the pool, each with
+    the window the row had before any of them were searched. Each of them gets a transposition table of its own that
+    falls back on the root's for reading, and is added to the root's in column order once the row is done. The
+    serialised searches for every subgame's starting bounds go on the pool too, as they don't use the table at all.
+    So nothing a job does depends on which thread ran it or when, and the search comes out the same however many
+    threads there are.
+*/
+
+use std::{
+    sync::{atomic::{AtomicBool, AtomicU64, Ordering}, RwLock},
+    time::{Duration, Instant}
+};
+use rand::{seq::SliceRandom, Rng};
+use crate::{
+    evaluation::Evaluator,
+    matrix_game,
+    parallel::{self, JobQueue},
+    simplex::{self, Constraint, Relation},
+    transposition::{Bound, TranspositionTable},
+    Board, Colour, GameState, Move
+};
+
+// Larger than any evaluation so a forced win or loss always dominates material
+pub const WIN_VALUE: f64 = 1000.0;
+
+// Every subgame value is strictly between -VALUE_BOUND and VALUE_BOUND
+const VALUE_BOUND: f64 = WIN_VALUE + u8::MAX as f64 + 1.0;
+
+// Width of the window used to test a subgame against a single bound
+const NULL_WINDOW: f64 = 1e-6;
+
+// The transposition table each of the root's cells is searched with has 2 ^ (4 * depth) entries, as shallow cells have
+// few subgames below them, up to 2 ^ MAX_CELL_TABLE_BITS and never more than the whole table
+const MAX_CELL_TABLE_BITS: u32 = 12;
+
+pub struct SearchResult {
+    pub moves: Vec<Move>,
+    pub strategy: Vec<f64>
+}
+
+impl SearchResult {
+    pub fn choose_move<R: Rng>(&self, rng: &mut R) -> Option<Move> {
+        let weighted_moves: Vec<(&Move, &f64)> = self.moves.iter().zip(self.strategy.iter()).collect();
+        match weighted_moves.choose_weighted(rng, |(_, prob)| **prob) {
+            Ok((mov, _)) => Some(**mov),
+            Err(_) => None
+        }
+    }
+}
+
+struct Subgame {
+    children: Vec<Vec<Board>>,
+    pessimistic: Vec<Vec<f64>>,
+    optimistic: Vec<Vec<f64>>,
+    remaining_rows: Vec<bool>,
+    remaining_columns: Vec<bool>
+}
+
+#[derive(Clone, Copy)]
+pub struct SearchLimits {
+    pub max_depth: u8,
+    pub time_budget: Option<Duration>,
+    pub node_budget: Option<u64>,
+    // Number of transposition table entries, 0 turns the table off
+    pub table_size: usize,
+    pub threads: usize
+}
+
+// Tracks how much of the search's time and node budget has been used
+struct Budget {
+    deadline: Option<Instant>,
+    node_budget: Option<u64>,
+    nodes: AtomicU64,
+    exhausted: AtomicBool
+}
+
+struct Searcher<'a, E: Evaluator> {
+    colour: Colour,
+    evaluator: &'a E,
+    budget: &'a Budget,
+    table: RwLock<TranspositionTable>,
+    // The searcher of one of the root's cells looks in the root's table for anything not in its own, and is None for
+    // the root's searcher
+    root_table: Option<&'a RwLock<TranspositionTable>>,
+    jobs: &'a JobQueue<Job, JobResult>
+}
+
+enum Job {
+    // The serialised bounds of a cell
+    Bounds { board: Board, depth: u8 },
+    // The value of one of the root's cells, searched with a table of its own
+    Cell { board: Board, depth: u8, alpha: f64, beta: f64 }
+}
+
+enum JobResult {
+    Bounds(f64, f64),
+    Cell(f64, TranspositionTable)
+}
+
+// Searches one move pair deeper each iteration until max_depth is reached or the budget runs out, and returns the
+// strategy from the deepest iteration that finished. Without a time or node budget this is a fixed depth search.
+pub fn search<E: Evaluator>(board: &Board, colour: Colour, limits: &SearchLimits, evaluator: &E) -> Option<SearchResult> {
+    let budget = Budget::new(limits);
+    if limits.time_budget.is_none() && limits.node_budget.is_none() {
+        return with_searcher(colour, evaluator, &budget, limits, |searcher| searcher.search_to_depth(board, limits.max_depth));
+    }
+    let best_result = with_searcher(colour, evaluator, &budget, limits, |searcher| {
+        let mut best_result = None;
+        for depth in 1..=limits.max_depth {
+            match searcher.search_to_depth(board, depth) {
+                Some(result) if !budget.is_exhausted() => best_result = Some(result),
+                _ => break
+            }
+        }
+        best_result
+    });
+    // Even the first iteration didn't finish, so fall back to the cheapest search there is
+    best_result.or_else(|| with_searcher(colour, evaluator, &Budget::unlimited(), limits, |searcher| searcher.search_to_depth(board, 1)))
+}
+
+// Runs search with a searcher and the pool of threads its jobs go to, which lasts until search returns
+fn with_searcher<E: Evaluator, T>(colour: Colour, evaluator: &E, budget: &Budget, limits: &SearchLimits, search: impl FnOnce(&Searcher<E>) -> T) -> T {
+    let jobs = JobQueue::new();
+    let searcher = Searcher::new(colour, evaluator, budget, limits.table_size, &jobs);
+    parallel::with_workers(limits.threads, &jobs, |job| searcher.run_job(job), || search(&searcher))
+}
+
+impl Budget {
+    fn new(limits: &SearchLimits) -> Budget {
+        Budget {
+            deadline: limits.time_budget.map(|time_budget| Instant::now() + time_budget),
+            node_budget: limits.node_budget,
+            nodes: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false)
+        }
+    }
+    fn unlimited() -> Budget {
+        Budget {
+            deadline: None,
+            node_budget: None,
+            nodes: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false)
+        }
+    }
+    // Counts a node and returns false once the budget has run out
+    fn visit_node(&self) -> bool {
+        let nodes = self.nodes.fetch_add(1, Ordering::Relaxed) + 1;
+        let out_of_nodes = self.node_budget.is_some_and(|node_budget| nodes > node_budget);
+        let out_of_time = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        if out_of_nodes || out_of_time {
+            self.exhausted.store(true, Ordering::Relaxed);
+        }
+        !self.is_exhausted()
+    }
+    fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+}
+
+impl<'a, E: Evaluator> Searcher<'a, E> {
+    fn new(colour: Colour, evaluator: &'a E, budget: &'a Budget, table_size: usize, jobs: &'a JobQueue<Job, JobResult>) -> Searcher<'a, E> {
+        Searcher {
+            colour,
+            evaluator,
+            budget,
+            table: RwLock::new(TranspositionTable::new(table_size)),
+            root_table: None,
+            jobs
+        }
+    }
+    // depth is the number of move pairs to look ahead, a depth of 1 only evaluates the boards after this move
+    fn search_to_depth(&self, board: &Board, depth: u8) -> Option<SearchResult> {
+        let all_our_moves = board.get_all_legal_moves(self.colour);
+        let all_opponent_moves = board.get_all_legal_moves(-self.colour);
+        if all_our_moves.is_empty() || all_opponent_moves.is_empty() {
+            return None;
+        }
+        let (value, strategy) = self.solve_subgame(board, depth, &all_our_moves, &all_opponent_moves, -VALUE_BOUND, VALUE_BOUND);
+        self.store(board, depth, value, -VALUE_BOUND, VALUE_BOUND, &strategy);
+        Some(SearchResult {
+            moves: all_our_moves,
+            strategy
+        })
+    }
+    fn get_subgame_value(&self, board: &Board, depth: u8, alpha: f64, beta: f64) -> f64 {
+        if let Some(value) = self.try_get_static_value(board, depth) {
+            return value;
+        }
+        // Once the budget has run out the value doesn't matter, as the whole iteration gets thrown away
+        if !self.budget.visit_node() {
+            return 0.0;
+        }
+        let all_our_moves = board.get_all_legal_moves(self.colour);
+        let all_opponent_moves = board.get_all_legal_moves(-self.colour);
+        if all_our_moves.is_empty() || all_opponent_moves.is_empty() {
+            return self.evaluator.evaluate(board, self.colour);
+        }
+        match self.get_stored_value(board, depth) {
+            Some((value, Bound::Exact)) => return value,
+            Some((value, Bound::Lower)) if value >= beta => return value,
+            Some((value, Bound::Upper)) if value <= alpha => return value,
+            _ => ()
+        }
+        let (value, strategy) = self.solve_subgame(board, depth, &all_our_moves, &all_opponent_moves, alpha, beta);
+        self.store(board, depth, value, alpha, beta, &strategy);
+        value
+    }
+    fn store(&self, board: &Board, depth: u8, value: f64, alpha: f64, beta: f64, strategy: &[f64]) {
+        // The value of an unfinished search is meaningless
+        if self.budget.is_exhausted() {
+            return;
+        }
+        // Outside the window all we know is which side of it the value is on
+        let (value, bound, strategy) = if value <= alpha {
+            (alpha, Bound::Upper, vec![])
+        } else if value >= beta {
+            (beta, Bound::Lower, vec![])
+        } else {
+            (value, Bound::Exact, strategy.to_vec())
+        };
+        self.table.write().unwrap().store(board, depth, value, bound, strategy);
+    }
+    // The value of boards that don't need searching, either because the game is over or there's no depth left
+    fn try_get_static_value(&self, board: &Board, depth: u8) -> Option<f64> {
+        match board.get_game_state() {
+            // Prefer quicker wins and slower losses by scaling with the depth left when the game ended
+            GameState::Win(winner, _) if winner == self.colour => Some(WIN_VALUE + depth as f64),
+            GameState::Win(..) => Some(-WIN_VALUE - depth as f64),
+            GameState::Draw(_) => Some(0.0),
+            GameState::Ongoing if depth == 0 => {
+                // Evaluations count as nodes too, so that a budget can run out during the first iteration
+                self.budget.visit_node();
+                Some(self.evaluator.evaluate(board, self.colour))
+            },
+            GameState::Ongoing => None
+        }
+    }
+    fn get_stored_value(&self, board: &Board, depth: u8) -> Option<(f64, Bound)> {
+        self.get_tables().find_map(|table| table.read().unwrap().get_value(board, depth))
+    }
+    fn get_stored_strategy(&self, board: &Board) -> Option<Vec<f64>> {
+        self.get_tables().find_map(|table| table.read().unwrap().get_strategy(board).map(|strategy| strategy.to_vec()))
+    }
+    // Our own table, then the root's if we're searching one of its cells
+    fn get_tables(&self) -> impl Iterator<Item = &RwLock<TranspositionTable>> {
+        [Some(&self.table), self.root_table].into_iter().flatten()
+    }
+    // Cells whose value is known without searching start off exact, the rest start with the bounds from their
+    // serialised searches, run on the thread pool
+    fn get_initial_bounds(&self, children: &[Vec<Board>], depth: u8) -> Vec<Vec<(f64, f64)>> {
+        let mut bounds: Vec<Vec<Option<(f64, f64)>>> = children
+            .iter()
+            .map(|row| row.iter().map(|child| self.try_get_known_value(child, depth).map(|value| (value, value))).collect())
+            .collect();
+        let unknown_cells: Vec<(usize, usize)> = (0..children.len())
+            .flat_map(|row| (0..children[row].len()).map(move |col| (row, col)))
+            .filter(|(row, col)| bounds[*row][*col].is_none())
+            .collect();
+        let jobs = unknown_cells.iter().map(|(row, col)| Job::Bounds { board: children[*row][*col], depth }).collect();
+        for ((row, col), result) in unknown_cells.into_iter().zip(self.run_jobs(jobs)) {
+            let JobResult::Bounds(pessimistic, optimistic) = result else {
+                unreachable!("a bounds job gave a cell's result");
+            };
+            bounds[row][col] = Some(match self.get_stored_value(&children[row][col], depth) {
+                Some((value, Bound::Lower)) => (pessimistic.max(value), optimistic),
+                Some((value, Bound::Upper)) => (pessimistic, optimistic.min(value)),
+                _ => (pessimistic, optimistic)
+            });
+        }
+        bounds.into_iter().map(|row| row.into_iter().map(Option::unwrap).collect()).collect()
+    }
+    fn try_get_known_value(&self, board: &Board, depth: u8) -> Option<f64> {
+        self.try_get_static_value(board, depth).or_else(|| match self.get_stored_value(board, depth) {
+            Some((value, Bound::Exact)) => Some(value),
+            _ => None
+        })
+    }
+    fn run_jobs(&self, jobs: Vec<Job>) -> Vec<JobResult> {
+        self.jobs.run_all(jobs, &|job| self.run_job(job))
+    }
+    // Works the same whichever searcher runs it, as the pool's threads and any searcher waiting on its own jobs
+    // all run jobs from the same queue
+    fn run_job(&self, job: Job) -> JobResult {
+        match job {
+            Job::Bounds { board, depth } => JobResult::Bounds(
+                self.get_serialised_value(&board, depth, true, -VALUE_BOUND, VALUE_BOUND),
+                self.get_serialised_value(&board, depth, false, -VALUE_BOUND, VALUE_BOUND)
+            ),
+            Job::Cell { board, depth, alpha, beta } => {
+                let root_table = self.root_table.unwrap_or(&self.table);
+                let table_size = root_table.read().unwrap().get_num_entries().min(1 << (4 * depth as u32).min(MAX_CELL_TABLE_BITS));
+                let cell_searcher = Searcher {
+                    colour: self.colour,
+                    evaluator: self.evaluator,
+                    budget: self.budget,
+                    table: RwLock::new(TranspositionTable::new(table_size)),
+                    root_table: Some(root_table),
+                    jobs: self.jobs
+                };
+                let value = cell_searcher.get_subgame_value(&board, depth, alpha, beta);
+                JobResult::Cell(value, cell_searcher.table.into_inner().unwrap())
+            }
+        }
+    }
+    // Alpha-beta over the game where at every move pair one side commits to its move first and the other side replies
+    // knowing it. When we_move_first this is a lower bound on the simultaneous value, otherwise an upper bound.
+    fn get_serialised_value(&self, board: &Board, depth: u8, we_move_first: bool, mut alpha: f64, mut beta: f64) -> f64 {
+        if let Some(value) = self.try_get_static_value(board, depth) {
+            return value;
+        }
+        if !self.budget.visit_node() {
+            return 0.0;
+        }
+        let colour = self.colour;
+        let all_our_moves = board.get_all_legal_moves(colour);
+        let all_opponent_moves = board.get_all_legal_moves(-colour);
+        if all_our_moves.is_empty() || all_opponent_moves.is_empty() {
+            return self.evaluator.evaluate(board, colour);
+        }
+        let get_child_value = |our_move: &Move, opp_move: &Move, alpha: f64, beta: f64| {
+            let child = board.get_boards_possible_for_move(our_move, &colour, &[*opp_move])[0];
+            self.get_serialised_value(&child, depth - 1, we_move_first, alpha, beta)
+        };
+        if we_move_first {
+            let mut best = -VALUE_BOUND;
+            for our_move in all_our_moves.iter() {
+                let mut worst_reply = VALUE_BOUND;
+                for opp_move in all_opponent_moves.iter() {
+                    worst_reply = worst_reply.min(get_child_value(our_move, opp_move, alpha, beta.min(worst_reply)));
+                    if worst_reply <= alpha {
+                        break;
+                    }
+                }
+                best = best.max(worst_reply);
+                alpha = alpha.max(best);
+                if best >= beta {
+                    break;
+                }
+            }
+            best
+        } else {
+            let mut best = VALUE_BOUND;
+            for opp_move in all_opponent_moves.iter() {
+                let mut best_reply = -VALUE_BOUND;
+                for our_move in all_our_moves.iter() {
+                    best_reply = best_reply.max(get_child_value(our_move, opp_move, alpha.max(best_reply), beta));
+                    if best_reply >= beta {
+                        break;
+                    }
+                }
+                best = best.min(best_reply);
+                beta = beta.min(best);
+                if best <= alpha {
+                    break;
+                }
+            }
+            best
+        }
+    }
+    // Returns the value and our strategy over all_our_moves. A value <= alpha only means the true value is <= alpha
+    // and a value >= beta only means it is >= beta, in which case the strategy is meaningless.
+    fn solve_subgame(&self, board: &Board, depth: u8, all_our_moves: &[Move], all_opponent_moves: &[Move], alpha: f64, beta: f64) -> (f64, Vec<f64>) {
+        let mut subgame = Subgame::new(self, board, depth, all_our_moves, all_opponent_moves);
+        // The root's cells are searched a whole row at a time on the thread pool, deeper down each cell is searched
+        // on its own so it gets the tightest window
+        let batches: Vec<Vec<usize>> = match self.root_table {
+            None => vec![(0..all_opponent_moves.len()).collect()],
+            Some(_) => (0..all_opponent_moves.len()).map(|col| vec![col]).collect()
+        };
+        for row in self.get_row_order(board, all_our_moves.len()) {
+            for batch in batches.iter() {
+                if self.budget.is_exhausted() {
+                    return (0.0, vec![]);
+                }
+                if !subgame.remaining_rows[row] {
+                    break;
+                }
+                let windows: Vec<(usize, f64, f64)> = batch
+                    .iter()
+                    .filter(|col| subgame.remaining_columns[**col] && !subgame.is_exact(row, **col))
+                    .map(|col| (*col, subgame.get_row_domination_bound(row, *col, alpha), subgame.get_column_domination_bound(row, *col, beta)))
+                    .collect();
+                let values = self.search_cells(&subgame, row, depth, &windows);
+                for ((col, row_alpha, col_beta), value) in windows.into_iter().zip(values) {
+                    // Once the row's gone the rest of its cells don't matter
+                    if !subgame.remaining_rows[row] {
+                        break;
+                    }
+                    if row_alpha >= col_beta {
+                        if value <= row_alpha {
+                            subgame.remaining_rows[row] = false;
+                        } else {
+                            subgame.remaining_columns[col] = false;
+                        }
+                    } else if value <= row_alpha {
+                        subgame.remaining_rows[row] = false;
+                    } else if value >= col_beta {
+                        subgame.remaining_columns[col] = false;
+                    } else {
+                        subgame.pessimistic[row][col] = value;
+                        subgame.optimistic[row][col] = value;
+                    }
+                }
+            }
+        }
+        if self.budget.is_exhausted() {
+            return (0.0, vec![]);
+        }
+        subgame.solve_remaining(alpha, beta)
+    }
+    // The values of row's cells searched with the given (col, row_alpha, col_beta) windows. At the root they're
+    // jobs, and their tables are added to ours in column order once they've all finished.
+    fn search_cells(&self, subgame: &Subgame, row: usize, depth: u8, windows: &[(usize, f64, f64)]) -> Vec<f64> {
+        let get_window = |row_alpha: f64, col_beta: f64| {
+            // Whatever the value is one of the two must be dominated, a null window search tells us which
+            if row_alpha >= col_beta {
+                (row_alpha, row_alpha + NULL_WINDOW)
+            } else {
+                (row_alpha, col_beta)
+            }
+        };
+        if self.root_table.is_some() {
+            return windows
+                .iter()
+                .map(|(col, row_alpha, col_beta)| {
+                    let (alpha, beta) = get_window(*row_alpha, *col_beta);
+                    self.get_subgame_value(&subgame.children[row][*col], depth - 1, alpha, beta)
+                })
+                .collect();
+        }
+        let jobs = windows
+            .iter()
+            .map(|(col, row_alpha, col_beta)| {
+                let (alpha, beta) = get_window(*row_alpha, *col_beta);
+                Job::Cell { board: subgame.children[row][*col], depth: depth - 1, alpha, beta }
+            })
+            .collect();
+        self.run_jobs(jobs)
+            .into_iter()
+            .map(|result| {
+                let JobResult::Cell(value, table) = result else {
+                    unreachable!("a cell job gave a cell's bounds");
+                };
+                self.table.write().unwrap().absorb(table);
+                value
+            })
+            .collect()
+    }
+    // Rows our last strategy here played most often are searched first, as they're the least likely to be pruned
+    // and searching them early gives tighter bounds for pruning the rest
+    fn get_row_order(&self, board: &Board, num_rows: usize) -> Vec<usize> {
+        let mut rows: Vec<usize> = (0..num_rows).collect();
+        if let Some(strategy) = self.get_stored_strategy(board) {
+            if strategy.len() == num_rows {
+                rows.sort_by(|a, b| strategy[*b].total_cmp(&strategy[*a]));
+            }
+        }
+        rows
+    }
+}
+
+impl Subgame {
+    fn new<E: Evaluator>(searcher: &Searcher<E>, board: &Board, depth: u8, all_our_moves: &[Move], all_opponent_moves: &[Move]) -> Subgame {
+        let children: Vec<Vec<Board>> = all_our_moves
+            .iter()
+            .map(|our_move| board.get_boards_possible_for_move(our_move, &searcher.colour, all_opponent_moves))
+            .collect();
+        let bounds = searcher.get_initial_bounds(&children, depth - 1);
+        Subgame {
+            pessimistic: bounds.iter().map(|row| row.iter().map(|(lower, _)| *lower).collect()).collect(),
+            optimistic: bounds.iter().map(|row| row.iter().map(|(_, upper)| *upper).collect()).collect(),
+            remaining_rows: vec![true; all_our_moves.len()],
+            remaining_columns: vec![true; all_opponent_moves.len()],
+            children
+        }
+    }
+    fn is_exact(&self, row: usize, col: usize) -> bool {
+        self.pessimistic[row][col] >= self.optimistic[row][col]
+    }
+    // The highest value in cell (row, col) at which a mix of the other rows (and alpha) still dominates row
+    fn get_row_domination_bound(&self, row: usize, col: usize, alpha: f64) -> f64 {
+        // Nothing can dominate a row while it has a cell with no upper bound yet, so don't bother with the LP
+        if alpha < VALUE_BOUND && self.get_other_columns(col).any(|other_col| self.optimistic[row][other_col] >= VALUE_BOUND) {
+            return -VALUE_BOUND;
+        }
+        let other_rows: Vec<usize> = (0..self.remaining_rows.len())
+            .filter(|other| *other != row && self.remaining_rows[*other])
+            .collect();
+        // Variables are the probability of each other row followed by the probability of the alpha row
+        let mut objective: Vec<f64> = other_rows.iter().map(|other| self.pessimistic[*other][col]).collect();
+        objective.push(alpha);
+        let mut constraints: Vec<Constraint> = self.get_other_columns(col)
+            .map(|other_col| {
+                let mut coefficients: Vec<f64> = other_rows.iter().map(|other| self.pessimistic[*other][other_col]).collect();
+                coefficients.push(alpha);
+                Constraint { coefficients, relation: Relation::GreaterOrEqual, rhs: self.optimistic[row][other_col] }
+            })
+            .collect();
+        constraints.push(Constraint { coefficients: vec![1.0; other_rows.len() + 1], relation: Relation::Equal, rhs: 1.0 });
+        match simplex::maximise(&objective, &constraints) {
+            Some(solution) => solution.objective,
+            None => -VALUE_BOUND
+        }
+    }
+    // The lowest value in cell (row, col) at which a mix of the other columns (and beta) still dominates col
+    fn get_column_domination_bound(&self, row: usize, col: usize, beta: f64) -> f64 {
+        let has_unbounded_cell = (0..self.remaining_rows.len())
+            .any(|other_row| other_row != row && self.remaining_rows[other_row] && self.pessimistic[other_row][col] <= -VALUE_BOUND);
+        if beta > -VALUE_BOUND && has_unbounded_cell {
+            return VALUE_BOUND;
+        }
+        let other_cols: Vec<usize> = self.get_other_columns(col).collect();
+        // Variables are the probability of each other column followed by the probability of the beta column
+        let mut objective: Vec<f64> = other_cols.iter().map(|other| -self.optimistic[row][*other]).collect();
+        objective.push(-beta);
+        let mut constraints: Vec<Constraint> = (0..self.remaining_rows.len())
+            .filter(|other_row| *other_row != row && self.remaining_rows[*other_row])
+            .map(|other_row| {
+                let mut coefficients: Vec<f64> = other_cols.iter().map(|other| self.optimistic[other_row][*other]).collect();
+                coefficients.push(beta);
+                Constraint { coefficients, relation: Relation::LessOrEqual, rhs: self.pessimistic[other_row][col] }
+            })
+            .collect();
+        constraints.push(Constraint { coefficients: vec![1.0; other_cols.len() + 1], relation: Relation::Equal, rhs: 1.0 });
+        match simplex::maximise(&objective, &constraints) {
+            Some(solution) => -solution.objective,
+            None => VALUE_BOUND
+        }
+    }
+    fn get_other_columns(&self, col: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.remaining_columns.len()).filter(move |other| *other != col && self.remaining_columns[*other])
+    }
+    fn solve_remaining(&self, alpha: f64, beta: f64) -> (f64, Vec<f64>) {
+        let rows: Vec<usize> = (0..self.remaining_rows.len()).filter(|row| self.remaining_rows[*row]).collect();
+        let cols: Vec<usize> = (0..self.remaining_columns.len()).filter(|col| self.remaining_columns[*col]).collect();
+        if rows.is_empty() {
+            return (alpha, vec![]);
+        }
+        if cols.is_empty() {
+            return (beta, vec![]);
+        }
+        // Every cell left has been searched, so the remaining bounds are exact values
+        let payoffs: Vec<Vec<f64>> = rows
+            .iter()
+            .map(|row| cols.iter().map(|col| self.pessimistic[*row][*col]).collect())
+            .collect();
+        let mut strategy = vec![0.0; self.remaining_rows.len()];
+        let Some(solution) = matrix_game::solve_game(&payoffs) else {
+            // Should the solver fail, the row with the best worst case still gives the root a move to play
+            let (best, value) = payoffs
+                .iter()
+                .map(|payoff_row| payoff_row.iter().copied().fold(f64::INFINITY, f64::min))
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            strategy[rows[best]] = 1.0;
+            return (value, strategy);
+        };
+        rows.iter()
+            .zip(solution.row_strategy)
+            .for_each(|(row, prob)| strategy[*row] = prob);
+        (solution.value, strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use crate::{dataset, evaluation::PieceSquareEvaluator, MovePair};
+
+    // Positions from random games, a few move pairs apart so they cover the opening through to the endgame
+    fn get_random_positions(seed: u64, count: usize) -> Vec<Board> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut positions = vec![];
+        while positions.len() < count {
+            let mut board = Board::new();
+            while matches!(board.get_game_state(), GameState::Ongoing) && positions.len() < count {
+                if rng.gen_bool(0.25) {
+                    positions.push(board);
+                }
+                let white_moves = board.get_all_legal_moves(Colour::White);
+                let black_moves = board.get_all_legal_moves(Colour::Black);
+                board.execute_moves(MovePair { white: *white_moves.choose(&mut rng).unwrap(), black: *black_moves.choose(&mut rng).unwrap() });
+            }
+        }
+        positions
+    }
+
+    fn get_limits(max_depth: u8, table_size: usize, threads: usize) -> SearchLimits {
+        SearchLimits { max_depth, time_budget: None, node_budget: None, table_size, threads }
+    }
+
+    fn get_searched_value(board: &Board, depth: u8, table_size: usize, threads: usize) -> (f64, Vec<f64>) {
+        let evaluator = PieceSquareEvaluator::default();
+        let limits = get_limits(depth, table_size, threads);
+        let all_our_moves = board.get_all_legal_moves(Colour::White);
+        let all_opponent_moves = board.get_all_legal_moves(Colour::Black);
+        with_searcher(Colour::White, &evaluator, &Budget::new(&limits), &limits, |searcher| {
+            searcher.solve_subgame(board, depth, &all_our_moves, &all_opponent_moves, -VALUE_BOUND, VALUE_BOUND)
+        })
+    }
+
+    // Backward induction over every cell with no pruning, bounds or table
+    fn get_unpruned_value(searcher: &Searcher<PieceSquareEvaluator>, board: &Board, depth: u8) -> f64 {
+        if let Some(value) = searcher.try_get_static_value(board, depth) {
+            return value;
+        }
+        let all_our_moves = board.get_all_legal_moves(Colour::White);
+        let all_opponent_moves = board.get_all_legal_moves(Colour::Black);
+        if all_our_moves.is_empty() || all_opponent_moves.is_empty() {
+            return searcher.evaluator.evaluate(board, Colour::White);
+        }
+        let payoffs: Vec<Vec<f64>> = all_our_moves
+            .iter()
+            .map(|our_move| {
+                board.get_boards_possible_for_move(our_move, &Colour::White, &all_opponent_moves)
+                    .iter()
+                    .map(|child| get_unpruned_value(searcher, child, depth - 1))
+                    .collect()
+            })
+            .collect();
+        matrix_game::solve_game(&payoffs).unwrap().value
+    }
+
+    #[test]
+    fn pruned_search_finds_the_same_value_as_unpruned_backward_induction() {
+        let evaluator = PieceSquareEvaluator::default();
+        let budget = Budget::unlimited();
+        let jobs = JobQueue::new();
+        let naive_searcher = Searcher::new(Colour::White, &evaluator, &budget, 0, &jobs);
+        let mut positions = get_random_positions(1, 8);
+        positions.extend(["n...p..............PN....", "....n.......p...N...P...P"].map(|board| dataset::parse_board(board).unwrap()));
+        for board in positions.iter() {
+            // The unpruned search is too slow to go deeper where there are lots of moves, like in the opening
+            let cells = board.get_all_legal_moves(Colour::White).len() * board.get_all_legal_moves(Colour::Black).len();
+            let max_depth = if cells <= 20 { 3 } else { 2 };
+            for depth in 1..=max_depth {
+                let unpruned = get_unpruned_value(&naive_searcher, board, depth);
+                for table_size in [0, 1 << 12] {
+                    let (value, _) = get_searched_value(board, depth, table_size, 1);
+                    assert!((value - unpruned).abs() < 1e-9, "{} against {} at depth {} for\n{}", value, unpruned, depth, board);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn search_comes_out_the_same_on_one_thread_as_on_four() {
+        let mut searches: Vec<(Board, u8)> = get_random_positions(2, 2).into_iter().map(|board| (board, 2)).collect();
+        searches.extend(["n...p..............PN....", "....n.......p...N...P...P"].map(|board| (dataset::parse_board(board).unwrap(), 3)));
+        for (board, depth) in searches.iter() {
+            for table_size in [0, 1 << 12] {
+                let (single_value, single_strategy) = get_searched_value(board, *depth, table_size, 1);
+                let (value, strategy) = get_searched_value(board, *depth, table_size, 4);
+                assert_eq!(value, single_value, "at depth {} for\n{}", depth, board);
+                assert_eq!(strategy, single_strategy, "at depth {} for\n{}", depth, board);
+            }
+        }
+    }
+
+    // Each search with the table goes on to use what the shallower ones left in it, like when deepening iteratively
+    #[test]
+    fn table_does_not_change_the_value() {
+        let evaluator = PieceSquareEvaluator::default();
+        let mut searches: Vec<(Board, u8)> = get_random_positions(3, 2).into_iter().map(|board| (board, 2)).collect();
+        searches.extend(["n...p..............PN....", "....n.......p...N...P...P"].map(|board| (dataset::parse_board(board).unwrap(), 3)));
+        for (board, max_depth) in searches.iter() {
+            let limits = get_limits(*max_depth, 1 << 12, 1);
+            with_searcher(Colour::White, &evaluator, &Budget::new(&limits), &limits, |searcher| {
+                let all_our_moves = board.get_all_legal_moves(Colour::White);
+                let all_opponent_moves = board.get_all_legal_moves(Colour::Black);
+                for depth in 1..=*max_depth {
+                    let (value, _) = searcher.solve_subgame(board, depth, &all_our_moves, &all_opponent_moves, -VALUE_BOUND, VALUE_BOUND);
+                    let (untabled_value, _) = get_searched_value(board, depth, 0, 1);
+                    assert!((value - untabled_value).abs() < 1e-9, "{} against {} at depth {} for\n{}", value, untabled_value, depth, board);
+                }
+            });
+        }
+    }
+
+    // The strategy from each iteration of deepening with no budget, and the nodes visited by the time it finished
+    fn get_iterations(board: &Board, limits: &SearchLimits) -> Vec<(Vec<f64>, u64)> {
+        let evaluator = PieceSquareEvaluator::default();
+        let budget = Budget::unlimited();
+        with_searcher(Colour::White, &evaluator, &budget, limits, |searcher| {
+            (1..=limits.max_depth)
+                .map(|depth| (searcher.search_to_depth(board, depth).unwrap().strategy, budget.nodes.load(Ordering::Relaxed)))
+                .collect()
+        })
+    }
+
+    fn get_budgeted_strategy(board: &Board, limits: &SearchLimits, node_budget: u64) -> Vec<f64> {
+        let limits = SearchLimits { node_budget: Some(node_budget), ..*limits };
+        search(board, Colour::White, &limits, &PieceSquareEvaluator::default()).unwrap().strategy
+    }
+
+    #[test]
+    fn node_budget_returns_the_deepest_iteration_that_finished() {
+        let board = dataset::parse_board("....n.......p...N...P...P").unwrap();
+        // One thread, so the nodes are visited in the same order every time
+        let limits = get_limits(3, 1 << 12, 1);
+        let iterations = get_iterations(&board, &limits);
+        assert_ne!(iterations[1].0, iterations[2].0, "the test needs iterations that disagree");
+        for depth in 1..3 {
+            let (strategy, nodes) = &iterations[depth - 1];
+            // Enough for this iteration but not the next
+            assert_eq!(&get_budgeted_strategy(&board, &limits, *nodes), strategy, "at depth {}", depth);
+            assert_eq!(&get_budgeted_strategy(&board, &limits, iterations[depth].1 - 1), strategy, "at depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn running_out_during_the_first_iteration_still_gives_a_whole_strategy() {
+        let board = Board::new();
+        let limits = get_limits(2, 1 << 12, 1);
+        let (first_strategy, nodes) = &get_iterations(&board, &limits)[0];
+        for node_budget in [0, nodes / 2, nodes - 1] {
+            let strategy = get_budgeted_strategy(&board, &limits, node_budget);
+            assert_eq!(strategy.len(), board.get_all_legal_moves(Colour::White).len());
+            assert!((strategy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert_eq!(&strategy, first_strategy);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+/*
+    Self-play: the computer playing itself to generate training data.
+
+    Each game is recorded as a line starting with # saying how it went, followed by a line for every position in it:
+
+        <board> <result> <white move> <black move> <white strategy> <black strategy>
+
+    The board and result (for White) are as in a dataset, so the output can be given straight to the tuner. The moves
+    are the pair that was played and each strategy lists every move the side had with its probability, as
+    a2-a3:0.2500,b1-c3:0.7500. A pawn redeployed from the last rank is written as a move from there to where it went,
+    as a5-c3, and a side passing under the --pass rule or waiting for the other side to redeploy is written as pass.
+    Games that reach the move pair limit are drawn. Games are also drawn by repetition or after --no-progress-limit
+    move pairs without a capture or pawn move, like games against the computer.
+
+    Game n is played with its own RNG seeded with seed + n, so games can run in parallel and still come out the same
+    for a given seed, as long as neither engine is limited by time.
+*/
+
+use std::{fs, process};
+use rand::{rngs::StdRng, SeedableRng};
+use crate::{
+    dataset, evaluation::EvaluatorChoice, history::GameHistory, parallel, search::SearchResult, Board, Colour, Engine, GameState,
+    MovePair, PassRule
+};
+
+pub struct SelfPlaySettings {
+    pub games: u64,
+    pub seed: u64,
+    pub jobs: usize,
+    pub max_move_pairs: u32,
+    pub no_progress_limit: u32,
+    pub pass_rule: PassRule,
+    pub white_engine: Engine,
+    pub black_engine: Engine,
+    pub evaluator: EvaluatorChoice,
+    pub output: Option<String>
+}
+
+struct PlayedPosition {
+    board: Board,
+    white_strategy: SearchResult,
+    black_strategy: SearchResult,
+    move_pair: MovePair
+}
+
+pub fn self_play(settings: &SelfPlaySettings) {
+    let game_indices: Vec<u64> = (0..settings.games).collect();
+    let games = parallel::map_in_parallel(settings.jobs, &game_indices, |game_index| {
+        let (positions, final_state) = play_game(settings, *game_index);
+        format_game(*game_index, &positions, final_state)
+    });
+    let record = games.concat();
+    match &settings.output {
+        Some(path) => fs::write(path, record).unwrap_or_else(|err| {
+            eprintln!("Couldn't write {}: {}", path, err);
+            process::exit(1)
+        }),
+        None => print!("{}", record)
+    }
+}
+
+// Returns every position played and how the game ended
+fn play_game(settings: &SelfPlaySettings, game_index: u64) -> (Vec<PlayedPosition>, GameState) {
+    let mut rng = StdRng::seed_from_u64(settings.seed.wrapping_add(game_index));
+    let mut board = Board::new();
+    board.pass_rule = settings.pass_rule;
+    let mut history = GameHistory::new(&board, settings.no_progress_limit, Some(settings.max_move_pairs));
+    let mut positions = vec![];
+    while matches!(history.get_game_state(), GameState::Ongoing) {
+        let white_strategy = board.get_strategy(Colour::White, &settings.white_engine, &settings.evaluator, &mut rng);
+        let black_strategy = board.get_strategy(Colour::Black, &settings.black_engine, &settings.evaluator, &mut rng);
+        // A side without a move has drawn the game, or passes, so both sides always have one here
+        let move_pair = MovePair {
+            white: white_strategy.choose_move(&mut rng).unwrap(),
+            black: black_strategy.choose_move(&mut rng).unwrap()
+        };
+        positions.push(PlayedPosition { board, white_strategy, black_strategy, move_pair });
+        board.execute_moves(move_pair);
+        history.push(&board);
+    }
+    (positions, history.get_game_state())
+}
+
+fn format_game(game_index: u64, positions: &[PlayedPosition], final_state: GameState) -> String {
+    let (outcome, result) = match final_state {
+        GameState::Win(Colour::White, reason) => (format!("white wins {}", reason), "1"),
+        GameState::Win(Colour::Black, reason) => (format!("black wins {}", reason), "0"),
+        GameState::Draw(reason) => (format!("draw, {},", reason), "0.5"),
+        // play_game only stops once the game is over
+        GameState::Ongoing => unreachable!()
+    };
+    let mut lines = vec![format!("# game {} {} after {} move pairs", game_index, outcome, positions.len())];
+    lines.extend(positions.iter().map(|position| {
+        format!(
+            "{} {} {} {} {} {}",
+            dataset::format_board(&position.board),
+            result,
+            position.move_pair.white,
+            position.move_pair.black,
+            format_strategy(&position.white_strategy),
+            format_strategy(&position.black_strategy)
+        )
+    }));
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn format_strategy(search_result: &SearchResult) -> String {
+    let moves: Vec<String> = search_result.moves
+        .iter()
+        .zip(search_result.strategy.iter())
+        .map(|(mov, prob)| format!("{}:{:.4}", mov, prob))
+        .collect();
+    moves.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mcts::{MctsSettings, Selection}, search::SearchLimits};
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        let settings = SelfPlaySettings {
+            games: 1,
+            seed: 7,
+            jobs: 1,
+            max_move_pairs: 20,
+            no_progress_limit: 50,
+            pass_rule: PassRule::Never,
+            white_engine: Engine::Matrix(SearchLimits { max_depth: 1, time_budget: None, node_budget: None, table_size: 1 << 10, threads: 2 }),
+            black_engine: Engine::Mcts(MctsSettings { iterations: 100, exploration: std::f64::consts::SQRT_2, selection: Selection::Ucb }),
+            evaluator: EvaluatorChoice::PieceSquare(Box::default()),
+            output: None
+        };
+        let (positions, final_state) = play_game(&settings, 3);
+        let (replayed_positions, replayed_final_state) = play_game(&settings, 3);
+        assert_eq!(format_game(3, &positions, final_state), format_game(3, &replayed_positions, replayed_final_state));
+    }
+}
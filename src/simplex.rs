@@ -0,0 +1,262 @@
+/*
+    Dense two-phase simplex for small linear programs.
+
+    Solves: maximise c.x subject to a list of linear constraints, with every x >= 0.
+    Phase one drives the artificial variables out to find a feasible basis, phase two optimises the real objective.
+    Bland's rule is used for choosing pivots so degenerate problems (common in matrix games) can't cycle.
+*/
+
+const EPSILON: f64 = 1e-9;
+
+// How far phase one may leave the artificials from zero, relative to the largest coefficient, and still be feasible
+const FEASIBILITY_TOLERANCE: f64 = 1e-7;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Relation {
+    LessOrEqual,
+    GreaterOrEqual,
+    Equal
+}
+
+pub struct Constraint {
+    pub coefficients: Vec<f64>,
+    pub relation: Relation,
+    pub rhs: f64
+}
+
+pub struct Solution {
+    pub variables: Vec<f64>,
+    // The dual value (shadow price) of each constraint, in the order the constraints were given
+    pub duals: Vec<f64>,
+    pub objective: f64
+}
+
+struct Tableau {
+    rows: Vec<Vec<f64>>,
+    objective: Vec<f64>,
+    basis: Vec<usize>
+}
+
+// Returns None when the program is infeasible or unbounded
+pub fn maximise(objective: &[f64], constraints: &[Constraint]) -> Option<Solution> {
+    let num_variables = objective.len();
+    let num_slacks = constraints.iter().filter(|c| c.relation != Relation::Equal).count();
+    let num_artificials = constraints.iter().filter(|c| needs_artificial(c)).count();
+    let num_columns = num_variables + num_slacks + num_artificials;
+    let first_artificial = num_variables + num_slacks;
+
+    let mut rows = vec![];
+    let mut basis = vec![];
+    // The column holding each constraint's slack or artificial, and what the dual is that column's reduced cost times
+    let mut dual_columns = vec![];
+    let mut next_slack = num_variables;
+    let mut next_artificial = first_artificial;
+    for constraint in constraints {
+        // Keep every rhs non-negative so the starting basis is feasible
+        let sign = if constraint.rhs < 0.0 { -1.0 } else { 1.0 };
+        let mut row = vec![0.0; num_columns + 1];
+        constraint.coefficients
+            .iter()
+            .enumerate()
+            .for_each(|(col, coefficient)| row[col] = sign * coefficient);
+        row[num_columns] = sign * constraint.rhs;
+        match constraint.relation {
+            Relation::LessOrEqual => {
+                row[next_slack] = sign;
+                dual_columns.push((next_slack, 1.0));
+                next_slack += 1;
+            },
+            Relation::GreaterOrEqual => {
+                row[next_slack] = -sign;
+                dual_columns.push((next_slack, -1.0));
+                next_slack += 1;
+            },
+            Relation::Equal => dual_columns.push((next_artificial, sign))
+        };
+        if needs_artificial(constraint) {
+            row[next_artificial] = 1.0;
+            basis.push(next_artificial);
+            next_artificial += 1;
+        } else {
+            basis.push(next_slack - 1);
+        }
+        rows.push(row);
+    }
+
+    let mut tableau = Tableau { rows, objective: vec![0.0; num_columns + 1], basis };
+
+    if num_artificials > 0 {
+        let phase_one_costs: Vec<f64> = (0..num_columns)
+            .map(|col| if col >= first_artificial { -1.0 } else { 0.0 })
+            .collect();
+        tableau.set_objective(&phase_one_costs);
+        tableau.optimise(num_columns)?;
+        // Rounding errors grow with the size of the coefficients, so the tolerance for what's left does too
+        let scale = constraints
+            .iter()
+            .flat_map(|c| c.coefficients.iter().chain([&c.rhs]))
+            .fold(1.0, |acc: f64, val| acc.max(val.abs()));
+        if tableau.objective[num_columns] < -FEASIBILITY_TOLERANCE * scale {
+            return None;
+        }
+        tableau.remove_artificials_from_basis(first_artificial);
+    }
+
+    let mut phase_two_costs = vec![0.0; num_columns];
+    phase_two_costs[..num_variables].copy_from_slice(objective);
+    tableau.set_objective(&phase_two_costs);
+    tableau.optimise(first_artificial)?;
+
+    let mut variables = vec![0.0; num_variables];
+    for (row, basic) in tableau.basis.iter().enumerate() {
+        if *basic < num_variables {
+            variables[*basic] = tableau.rows[row][num_columns];
+        }
+    }
+    let duals = dual_columns
+        .into_iter()
+        .map(|(col, sign)| sign * tableau.objective[col])
+        .collect();
+    Some(Solution {
+        objective: tableau.objective[num_columns],
+        variables,
+        duals
+    })
+}
+
+fn needs_artificial(constraint: &Constraint) -> bool {
+    match constraint.relation {
+        Relation::LessOrEqual => constraint.rhs < 0.0,
+        Relation::GreaterOrEqual => constraint.rhs >= 0.0,
+        Relation::Equal => true
+    }
+}
+
+impl Tableau {
+    fn rhs_column(&self) -> usize {
+        self.objective.len() - 1
+    }
+    fn set_objective(&mut self, costs: &[f64]) {
+        // Objective row holds the reduced costs (negated, so a negative entry means the column can improve it)
+        // and the current objective value in the rhs column
+        let rhs = self.rhs_column();
+        self.objective = costs.iter().map(|cost| -cost).collect();
+        self.objective.push(0.0);
+        for (row, basic) in self.basis.iter().enumerate() {
+            let cost = costs[*basic];
+            if cost != 0.0 {
+                for col in 0..=rhs {
+                    self.objective[col] += cost * self.rows[row][col];
+                }
+            }
+        }
+    }
+    // Only columns below allowed_columns may enter the basis, which is how phase two ignores the artificials
+    fn optimise(&mut self, allowed_columns: usize) -> Option<()> {
+        let rhs = self.rhs_column();
+        loop {
+            let Some(entering) = (0..allowed_columns).find(|col| self.objective[*col] < -EPSILON) else {
+                return Some(());
+            };
+            let leaving = (0..self.rows.len())
+                .filter(|row| self.rows[*row][entering] > EPSILON)
+                .min_by(|a, b| {
+                    let ratio_a = self.rows[*a][rhs] / self.rows[*a][entering];
+                    let ratio_b = self.rows[*b][rhs] / self.rows[*b][entering];
+                    if (ratio_a - ratio_b).abs() < EPSILON {
+                        self.basis[*a].cmp(&self.basis[*b])
+                    } else {
+                        ratio_a.total_cmp(&ratio_b)
+                    }
+                })?;
+            self.pivot(leaving, entering);
+        }
+    }
+    fn remove_artificials_from_basis(&mut self, first_artificial: usize) {
+        // An artificial can still be basic at value 0 after phase one. Swap it for any real column in its row,
+        // and if there isn't one the row was a redundant constraint and can be dropped.
+        let mut row = 0;
+        while row < self.rows.len() {
+            if self.basis[row] >= first_artificial {
+                match (0..first_artificial).find(|col| self.rows[row][*col].abs() > EPSILON) {
+                    Some(col) => self.pivot(row, col),
+                    None => {
+                        self.rows.remove(row);
+                        self.basis.remove(row);
+                        continue;
+                    }
+                }
+            }
+            row += 1;
+        }
+    }
+    fn pivot(&mut self, pivot_row: usize, pivot_col: usize) {
+        let pivot = self.rows[pivot_row][pivot_col];
+        self.rows[pivot_row].iter_mut().for_each(|val| *val /= pivot);
+        let pivot_values = self.rows[pivot_row].clone();
+        let eliminate = |values: &mut Vec<f64>| {
+            let factor = values[pivot_col];
+            if factor != 0.0 {
+                values
+                    .iter_mut()
+                    .zip(pivot_values.iter())
+                    .for_each(|(val, pivot_val)| *val -= factor * pivot_val);
+            }
+        };
+        for (row, values) in self.rows.iter_mut().enumerate() {
+            if row != pivot_row {
+                eliminate(values);
+            }
+        }
+        eliminate(&mut self.objective);
+        self.basis[pivot_row] = pivot_col;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraint(coefficients: &[f64], relation: Relation, rhs: f64) -> Constraint {
+        Constraint { coefficients: coefficients.to_vec(), relation, rhs }
+    }
+
+    #[test]
+    fn finds_the_optimum_and_its_duals() {
+        // maximise 3x + 2y with x + y <= 4 and x + 3y <= 6, which is at x = 4, y = 0
+        let constraints = [constraint(&[1.0, 1.0], Relation::LessOrEqual, 4.0), constraint(&[1.0, 3.0], Relation::LessOrEqual, 6.0)];
+        let solution = maximise(&[3.0, 2.0], &constraints).unwrap();
+        assert!((solution.objective - 12.0).abs() < 1e-9);
+        assert!((solution.variables[0] - 4.0).abs() < 1e-9 && solution.variables[1].abs() < 1e-9);
+        assert!((solution.duals[0] - 3.0).abs() < 1e-9 && solution.duals[1].abs() < 1e-9);
+    }
+
+    #[test]
+    fn handles_equality_and_greater_or_equal_constraints() {
+        // maximise -x - y with x + y = 1 and x >= 0.25
+        let constraints = [constraint(&[1.0, 1.0], Relation::Equal, 1.0), constraint(&[1.0, 0.0], Relation::GreaterOrEqual, 0.25)];
+        let solution = maximise(&[-1.0, -1.0], &constraints).unwrap();
+        assert!((solution.objective + 1.0).abs() < 1e-9);
+        assert!(solution.variables[0] >= 0.25 - 1e-9);
+    }
+
+    #[test]
+    fn degenerate_program_doesnt_cycle() {
+        // Every constraint is tight at the origin, the kind of tie Bland's rule is there to break
+        let constraints = [
+            constraint(&[1.0, -1.0], Relation::LessOrEqual, 0.0),
+            constraint(&[-1.0, 1.0], Relation::LessOrEqual, 0.0),
+            constraint(&[1.0, 1.0], Relation::LessOrEqual, 2.0)
+        ];
+        let solution = maximise(&[1.0, 1.0], &constraints).unwrap();
+        assert!((solution.objective - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn infeasible_or_unbounded_programs_have_no_solution() {
+        let infeasible = [constraint(&[1.0], Relation::LessOrEqual, 1.0), constraint(&[1.0], Relation::GreaterOrEqual, 2.0)];
+        assert!(maximise(&[1.0], &infeasible).is_none());
+        let unbounded = [constraint(&[1.0, -1.0], Relation::LessOrEqual, 1.0)];
+        assert!(maximise(&[1.0, 1.0], &unbounded).is_none());
+    }
+}
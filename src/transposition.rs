@@ -0,0 +1,150 @@
+/*
+    Transposition table for the matrix search.
+
+    Different move pairs often lead to the same board, so subgame results are kept by the board's Zobrist hash and
+    reused when the board comes up again. The table has a fixed number of slots and each board goes in the slot its
+    hash picks. When two boards want the same slot the one searched deeper is kept, as it cost more to work out.
+*/
+
+use crate::Board;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    // The subgame is worth at least the value
+    Lower,
+    // The subgame is worth at most the value
+    Upper
+}
+
+struct Entry {
+    hash: u64,
+    depth: u8,
+    value: f64,
+    bound: Bound,
+    strategy: Vec<f64>
+}
+
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>
+}
+
+impl TranspositionTable {
+    // A table with no slots never stores anything, which turns it off
+    pub fn new(num_entries: usize) -> TranspositionTable {
+        TranspositionTable {
+            entries: (0..num_entries).map(|_| None).collect()
+        }
+    }
+    // Values are only reused at the same depth, as a subgame's value changes with how far ahead it was searched
+    pub fn get_value(&self, board: &Board, depth: u8) -> Option<(f64, Bound)> {
+        match self.get_entry(board) {
+            Some(entry) if entry.depth == depth => Some((entry.value, entry.bound)),
+            _ => None
+        }
+    }
+    // The strategy from the last time the subgame was solved exactly, at any depth
+    pub fn get_strategy(&self, board: &Board) -> Option<&[f64]> {
+        match self.get_entry(board) {
+            Some(entry) if !entry.strategy.is_empty() => Some(&entry.strategy),
+            _ => None
+        }
+    }
+    pub fn store(&mut self, board: &Board, depth: u8, value: f64, bound: Bound, strategy: Vec<f64>) {
+        self.store_entry(Entry { hash: board.hash, depth, value, bound, strategy });
+    }
+    // Stores every entry of other as if it had been stored here, in slot order
+    pub fn absorb(&mut self, other: TranspositionTable) {
+        for entry in other.entries.into_iter().flatten() {
+            self.store_entry(entry);
+        }
+    }
+    pub fn get_num_entries(&self) -> usize {
+        self.entries.len()
+    }
+    fn store_entry(&mut self, new_entry: Entry) {
+        let Some(slot) = self.get_index(new_entry.hash).map(|index| &mut self.entries[index]) else {
+            return;
+        };
+        let should_replace = match slot {
+            Some(entry) => entry.hash == new_entry.hash || new_entry.depth >= entry.depth,
+            None => true
+        };
+        if !should_replace {
+            return;
+        }
+        // Keep the old strategy for ordering moves if we've only learnt a bound this time
+        let strategy = match slot.take() {
+            Some(entry) if entry.hash == new_entry.hash && new_entry.strategy.is_empty() => entry.strategy,
+            _ => new_entry.strategy
+        };
+        *slot = Some(Entry { strategy, ..new_entry });
+    }
+    fn get_entry(&self, board: &Board) -> Option<&Entry> {
+        let index = self.get_index(board.hash)?;
+        self.entries[index].as_ref().filter(|entry| entry.hash == board.hash)
+    }
+    fn get_index(&self, hash: u64) -> Option<usize> {
+        match self.entries.len() {
+            0 => None,
+            num_entries => Some((hash % num_entries as u64) as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset;
+
+    fn get_boards() -> (Board, Board) {
+        (Board::new(), dataset::parse_board("n...p..............PN....").unwrap())
+    }
+
+    #[test]
+    fn deeper_entry_is_kept_over_a_shallower_one_for_another_board() {
+        let (board, other_board) = get_boards();
+        // With one slot every board wants the same one
+        let mut table = TranspositionTable::new(1);
+        table.store(&board, 3, 1.0, Bound::Exact, vec![1.0]);
+        table.store(&other_board, 2, 2.0, Bound::Exact, vec![1.0]);
+        assert!(table.get_value(&board, 3) == Some((1.0, Bound::Exact)));
+        assert!(table.get_value(&other_board, 2).is_none());
+        table.store(&other_board, 3, 2.0, Bound::Exact, vec![1.0]);
+        assert!(table.get_value(&board, 3).is_none());
+        assert!(table.get_value(&other_board, 3) == Some((2.0, Bound::Exact)));
+    }
+
+    #[test]
+    fn same_board_is_replaced_even_by_a_shallower_entry() {
+        let (board, _) = get_boards();
+        let mut table = TranspositionTable::new(1);
+        table.store(&board, 3, 1.0, Bound::Exact, vec![0.25, 0.75]);
+        table.store(&board, 1, -1.0, Bound::Upper, vec![]);
+        assert!(table.get_value(&board, 3).is_none());
+        assert!(table.get_value(&board, 1) == Some((-1.0, Bound::Upper)));
+        // Only a bound was learnt, so the strategy from before is still there for ordering moves
+        assert_eq!(table.get_strategy(&board), Some([0.25, 0.75].as_slice()));
+    }
+
+    #[test]
+    fn absorbed_entries_follow_the_same_rules() {
+        let (board, other_board) = get_boards();
+        let mut table = TranspositionTable::new(1);
+        table.store(&board, 3, 1.0, Bound::Exact, vec![1.0]);
+        let mut other_table = TranspositionTable::new(1);
+        other_table.store(&other_board, 2, 2.0, Bound::Exact, vec![1.0]);
+        table.absorb(other_table);
+        assert!(table.get_value(&board, 3) == Some((1.0, Bound::Exact)));
+        assert!(table.get_value(&other_board, 2).is_none());
+    }
+
+    #[test]
+    fn table_with_no_slots_stores_nothing() {
+        let (board, _) = get_boards();
+        let mut table = TranspositionTable::new(0);
+        table.store(&board, 1, 1.0, Bound::Exact, vec![1.0]);
+        assert!(table.get_value(&board, 1).is_none());
+        assert!(table.get_strategy(&board).is_none());
+    }
+}
@@ -0,0 +1,274 @@
+/*
+    Texel style tuning of the piece-square tables.
+
+    The evaluation is turned into a predicted result for White with a logistic curve, sigmoid(scale * value), and
+    the tables are fitted by gradient descent to minimise the log loss of those predictions against how the games
+    the positions came from actually ended. The scale is fitted first, with the starting tables, so that the
+    tuned values stay in the same units as the tables they started from.
+
+    Every table entry is tuned on its own, so the tables don't need to keep the shape of the hand written ones and
+    White and Black's pawn tables can end up different. Entries for squares no position in the dataset has a piece
+    on never get a gradient and keep their starting value.
+
+    For a given board the evaluation is linear in the table entries. Each piece counts its entry, less the share of
+    it the pawn safety term takes off a pawn the opponent can capture. The rest of the evaluation (the footmen, the
+    rest of pawn safety and the weighted terms from the pieces' moves) doesn't depend on the tables at all, so it's
+    worked out once for each position and added to its prediction as a fixed offset. The term weights aren't tuned
+    and are written out as they were given.
+*/
+
+use std::fs;
+use crate::{
+    dataset,
+    evaluation::{Evaluator, PieceSquareEvaluator, TermWeights, THREATENED_PAWN_SHARE},
+    Board, Colour, Kind, Move, SquareVal
+};
+
+// Predictions are kept this far from 0 and 1 so the loss of a confidently wrong prediction stays finite
+const MIN_PROBABILITY: f64 = 1e-12;
+const SCALE_SEARCH_STEPS: u32 = 100;
+const PROGRESS_INTERVAL: u32 = 100;
+
+pub struct TuneSettings {
+    pub dataset: String,
+    pub initial_tables: PieceSquareEvaluator,
+    pub output: Option<String>,
+    pub epochs: u32,
+    pub learning_rate: f64
+}
+
+// A position's evaluation for White is its offset plus the sum of the table entries (parameters) for its pieces,
+// each times its piece's coefficient
+struct Sample {
+    features: Vec<(usize, f64)>,
+    offset: f64,
+    result: f64
+}
+
+pub fn tune(settings: &TuneSettings) {
+    let positions = dataset::read_dataset(&settings.dataset).unwrap_or_else(|err| dataset::exit_with_error(&err));
+    if positions.is_empty() {
+        dataset::exit_with_error(&format!("{} has no positions in it", settings.dataset));
+    }
+    let mut params = get_params(&settings.initial_tables);
+    let samples: Vec<Sample> = positions
+        .iter()
+        .map(|position| get_sample(&position.board, position.result, &settings.initial_tables, &params))
+        .collect();
+    let scale = fit_scale(&samples, &params);
+    eprintln!("{} positions, scale {:.4}, starting loss {:.6}", samples.len(), scale, get_loss(&samples, &params, scale));
+    // The gradient grows with the square of the scale, so the step is divided by it to make the learning rate
+    // work the same whatever units the tables are in
+    let step_size = settings.learning_rate / (scale * scale);
+    for epoch in 1..=settings.epochs {
+        let gradient = get_gradient(&samples, &params, scale);
+        params
+            .iter_mut()
+            .zip(gradient)
+            .for_each(|(param, grad)| *param -= step_size * grad);
+        if epoch % PROGRESS_INTERVAL == 0 || epoch == settings.epochs {
+            eprintln!("Epoch {}, loss {:.6}", epoch, get_loss(&samples, &params, scale));
+        }
+    }
+    let tables = get_tables(&params, settings.initial_tables.weights).get_toml();
+    match &settings.output {
+        Some(path) => fs::write(path, tables).unwrap_or_else(|err| dataset::exit_with_error(&format!("Couldn't write {}: {}", path, err))),
+        None => print!("{}", tables)
+    }
+}
+
+// The offset is whatever's left of the evaluation with the starting tables once their entries are taken out
+fn get_sample(board: &Board, result: f64, initial_tables: &PieceSquareEvaluator, initial_params: &[f64]) -> Sample {
+    let features = get_features(board);
+    let offset = initial_tables.evaluate(board, Colour::White) - get_table_value(&features, initial_params);
+    Sample { features, offset, result }
+}
+
+// Each piece's entry is added for White and subtracted for Black, with a pawn the opponent can capture only keeping
+// the share of its entry the pawn safety term doesn't take back off
+fn get_features(board: &Board) -> Vec<(usize, f64)> {
+    let capturable_squares = [Colour::White, Colour::Black].map(|colour| get_target_squares(board, -colour));
+    board.board
+        .iter()
+        .enumerate()
+        .filter_map(|(index, square_val)| match square_val {
+            SquareVal::Piece(piece) => {
+                let (pawn_table, sign, capturable_squares) = match piece.colour {
+                    Colour::White => (0, 1.0, &capturable_squares[0]),
+                    Colour::Black => (1, -1.0, &capturable_squares[1])
+                };
+                match piece.kind {
+                    Kind::Knight => Some((2 * 63 + index, sign)),
+                    Kind::Pawn if capturable_squares.contains(&(index as u8)) => {
+                        Some((pawn_table * 63 + index, sign * (1.0 - THREATENED_PAWN_SHARE)))
+                    },
+                    Kind::Pawn => Some((pawn_table * 63 + index, sign))
+                }
+            },
+            _ => None
+        })
+        .collect()
+}
+
+// Every square one of colour's pieces can move to next
+fn get_target_squares(board: &Board, colour: Colour) -> Vec<u8> {
+    board.get_all_legal_moves(colour)
+        .iter()
+        .filter_map(|mov| match mov {
+            Move::Piece(piece_move) => Some(piece_move.to_square),
+            Move::Pass => None
+        })
+        .collect()
+}
+
+fn get_table_value(features: &[(usize, f64)], params: &[f64]) -> f64 {
+    features.iter().map(|(param, coefficient)| coefficient * params[*param]).sum()
+}
+
+fn get_params(tables: &PieceSquareEvaluator) -> Vec<f64> {
+    [tables.pawn_values_white, tables.pawn_values_black, tables.knight_values].concat()
+}
+
+fn get_tables(params: &[f64], weights: TermWeights) -> PieceSquareEvaluator {
+    let get_table = |table: usize| params[table * 63..(table + 1) * 63].try_into().unwrap();
+    PieceSquareEvaluator {
+        pawn_values_white: get_table(0),
+        pawn_values_black: get_table(1),
+        knight_values: get_table(2),
+        weights
+    }
+}
+
+fn get_prediction(sample: &Sample, params: &[f64], scale: f64) -> f64 {
+    let value = sample.offset + get_table_value(&sample.features, params);
+    let prediction = 1.0 / (1.0 + (-scale * value).exp());
+    prediction.clamp(MIN_PROBABILITY, 1.0 - MIN_PROBABILITY)
+}
+
+fn get_loss(samples: &[Sample], params: &[f64], scale: f64) -> f64 {
+    let total: f64 = samples
+        .iter()
+        .map(|sample| {
+            let prediction = get_prediction(sample, params, scale);
+            -(sample.result * prediction.ln() + (1.0 - sample.result) * (1.0 - prediction).ln())
+        })
+        .sum();
+    total / samples.len() as f64
+}
+
+fn get_gradient(samples: &[Sample], params: &[f64], scale: f64) -> Vec<f64> {
+    let mut gradient = vec![0.0; params.len()];
+    for sample in samples.iter() {
+        // The derivative of the log loss with respect to the evaluation
+        let error = scale * (get_prediction(sample, params, scale) - sample.result);
+        for (param, coefficient) in sample.features.iter() {
+            gradient[*param] += error * coefficient;
+        }
+    }
+    gradient.iter().map(|grad| grad / samples.len() as f64).collect()
+}
+
+// The loss is convex in the scale, so a ternary search over its logarithm finds the best one
+fn fit_scale(samples: &[Sample], params: &[f64]) -> f64 {
+    let (mut low, mut high) = (-10.0_f64, 5.0_f64);
+    for _ in 0..SCALE_SEARCH_STEPS {
+        let lower_third = low + (high - low) / 3.0;
+        let upper_third = high - (high - low) / 3.0;
+        if get_loss(samples, params, lower_third.exp()) <= get_loss(samples, params, upper_third.exp()) {
+            high = upper_third;
+        } else {
+            low = lower_third;
+        }
+    }
+    ((low + high) / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn offset_and_table_entries_add_up_to_the_evaluation_with_any_tables() {
+        let initial_tables = PieceSquareEvaluator::default();
+        let initial_params = get_params(&initial_tables);
+        let mut rng = StdRng::seed_from_u64(0);
+        let params: Vec<f64> = initial_params.iter().map(|param| param + rng.gen_range(-1.0..1.0)).collect();
+        let tables = get_tables(&params, initial_tables.weights);
+        // The last has a pawn on each side the other can capture
+        let boards = [
+            dataset::format_board(&Board::new()),
+            "n...p..............PN....".to_string(),
+            ".n... ..p.. .P... ..... ...N.".replace(' ', "")
+        ];
+        for board in boards.iter() {
+            let board = dataset::parse_board(board).unwrap();
+            let sample = get_sample(&board, 1.0, &initial_tables, &initial_params);
+            let value = sample.offset + get_table_value(&sample.features, &params);
+            let evaluation = tables.evaluate(&board, Colour::White);
+            assert!((value - evaluation).abs() < 1e-9, "{} against {} for\n{}", value, evaluation, board);
+        }
+    }
+
+    fn get_samples(positions: &[(&str, f64)]) -> Vec<Sample> {
+        let tables = PieceSquareEvaluator::default();
+        let params = get_params(&tables);
+        positions
+            .iter()
+            .map(|(board, result)| get_sample(&dataset::parse_board(&board.replace(' ', "")).unwrap(), *result, &tables, &params))
+            .collect()
+    }
+
+    #[test]
+    fn table_entry_moves_towards_how_the_games_ended() {
+        let params = get_params(&PieceSquareEvaluator::default());
+        // White's knight is on c3 in every position, and no piece is ever on a5
+        let knight_on_c3 = 2 * 63 + 31;
+        let knight_on_a5 = 2 * 63 + 15;
+        for (result, direction) in [(1.0, 1.0), (0.0, -1.0)] {
+            let samples = get_samples(&[("....p ..... ..N.. ..... P....", result), ("..p.. ..... ..N.. ....P .....", result)]);
+            let mut tuned = params.clone();
+            for _ in 0..10 {
+                let gradient = get_gradient(&samples, &tuned, 1.0);
+                tuned.iter_mut().zip(gradient).for_each(|(param, grad)| *param -= grad);
+            }
+            assert!((tuned[knight_on_c3] - params[knight_on_c3]) * direction > 0.0);
+            assert_eq!(tuned[knight_on_a5], params[knight_on_a5]);
+            assert!(get_loss(&samples, &tuned, 1.0) < get_loss(&samples, &params, 1.0));
+        }
+    }
+
+    #[test]
+    fn gradient_matches_finite_differences_of_the_loss() {
+        let samples = get_samples(&[
+            ("n...p ..... ..... ....P N....", 1.0),
+            (".n... ..p.. .P... ..... ...N.", 0.0),
+            ("..n.p .p... ..... ...P. NP...", 0.5)
+        ]);
+        let mut rng = StdRng::seed_from_u64(1);
+        let params: Vec<f64> = get_params(&PieceSquareEvaluator::default()).iter().map(|param| param + rng.gen_range(-0.5..0.5)).collect();
+        let scale = 0.7;
+        let gradient = get_gradient(&samples, &params, scale);
+        let step = 1e-6;
+        for (index, grad) in gradient.iter().enumerate() {
+            let mut changed = params.clone();
+            changed[index] += step;
+            let upper_loss = get_loss(&samples, &changed, scale);
+            changed[index] -= 2.0 * step;
+            let lower_loss = get_loss(&samples, &changed, scale);
+            let expected = (upper_loss - lower_loss) / (2.0 * step);
+            assert!((grad - expected).abs() < 1e-6, "entry {} has gradient {} but the loss changes at {}", index, grad, expected);
+        }
+    }
+
+    #[test]
+    fn fitted_scale_is_the_one_the_results_were_made_with() {
+        // Positions whose results are exactly the predictions at a scale of 0.5
+        let samples: Vec<Sample> = [1.0, -2.0, 4.0]
+            .iter()
+            .map(|value| Sample { features: vec![], offset: *value, result: 1.0 / (1.0 + (-0.5 * value).exp()) })
+            .collect();
+        let params = get_params(&PieceSquareEvaluator::default());
+        assert!((fit_scale(&samples, &params) - 0.5).abs() < 1e-6);
+    }
+}
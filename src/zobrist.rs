@@ -0,0 +1,57 @@
+/*
+    Zobrist hashing for boards.
+
+    Every (square, piece) combination has a random 64 bit key and a board's hash is the XOR of the keys of every
+    piece on it. Moving a piece only needs its old and new keys XORed in, so Board keeps its hash up to date as
+    squares change rather than rehashing all 63 squares.
+*/
+
+use crate::{Colour, Kind, Piece, SquareVal};
+
+const PIECE_KEYS: [[u64; 4]; 63] = generate_piece_keys();
+
+// The keys are fixed at compile time (splitmix64 from a constant seed) so hashes are the same on every run
+const fn generate_piece_keys() -> [[u64; 4]; 63] {
+    let mut keys = [[0; 4]; 63];
+    let mut state: u64 = 0x9e3779b97f4a7c15;
+    let mut square = 0;
+    while square < 63 {
+        let mut piece = 0;
+        while piece < 4 {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut key = state;
+            key = (key ^ (key >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            key = (key ^ (key >> 27)).wrapping_mul(0x94d049bb133111eb);
+            keys[square][piece] = key ^ (key >> 31);
+            piece += 1;
+        }
+        square += 1;
+    }
+    keys
+}
+
+pub fn get_square_key(square_val: &SquareVal, index: usize) -> u64 {
+    match square_val {
+        SquareVal::Piece(piece) => PIECE_KEYS[index][get_piece_index(piece)],
+        _ => 0
+    }
+}
+
+pub fn hash_squares(squares: &[SquareVal; 63]) -> u64 {
+    squares
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (index, square_val)| hash ^ get_square_key(square_val, index))
+}
+
+fn get_piece_index(piece: &Piece) -> usize {
+    let colour_index = match piece.colour {
+        Colour::White => 0,
+        Colour::Black => 2
+    };
+    let kind_index = match piece.kind {
+        Kind::Pawn => 0,
+        Kind::Knight => 1
+    };
+    colour_index + kind_index
+}